@@ -0,0 +1,24 @@
+-- Columns read and written by the API since Bids are checked against the
+-- signer's other submitted Bids, can carry a deadline and can carry a nonce.
+-- Safe to run more than once.
+BEGIN;
+
+-- Lowercase hex without 0x, like auction_address
+ALTER TABLE bids ADD COLUMN IF NOT EXISTS settlement_contract TEXT;
+ALTER TABLE bids ADD COLUMN IF NOT EXISTS base_price NUMERIC;
+-- At most one is set, for version 2 Bids with a deadline
+ALTER TABLE bids ADD COLUMN IF NOT EXISTS deadline_block BIGINT;
+ALTER TABLE bids ADD COLUMN IF NOT EXISTS deadline_timestamp BIGINT;
+ALTER TABLE bids ADD COLUMN IF NOT EXISTS nonce NUMERIC;
+
+-- Filled with the settlement contract and base price of every auction that
+-- still has submitted Bids without them, before running 0002
+CREATE TABLE IF NOT EXISTS bid_backfill_auctions (
+    auction_address TEXT NOT NULL,
+    auction_name TEXT NOT NULL,
+    settlement_contract TEXT NOT NULL,
+    base_price NUMERIC NOT NULL,
+    PRIMARY KEY (auction_address, auction_name)
+);
+
+COMMIT;
@@ -0,0 +1,36 @@
+-- Fills settlement_contract and base_price of submitted Bids stored before the
+-- columns existed, so they count towards their signer's exposure. Addresses in
+-- bid_backfill_auctions can be checksummed and 0x-prefixed, and base prices are
+-- in wei. Run once it has a row for every auction listed by:
+--
+--   SELECT DISTINCT auction_address, auction_name FROM bids
+--   WHERE status = 'submitted'
+--       AND (settlement_contract IS NULL OR base_price IS NULL);
+BEGIN;
+
+UPDATE bids SET
+    settlement_contract = LOWER(REGEXP_REPLACE(backfill.settlement_contract, '^0x', '')),
+    base_price = backfill.base_price
+FROM bid_backfill_auctions backfill
+WHERE
+    bids.auction_address = LOWER(REGEXP_REPLACE(backfill.auction_address, '^0x', ''))
+    AND bids.auction_name = backfill.auction_name
+    AND bids.status = 'submitted'
+    AND (bids.settlement_contract IS NULL OR bids.base_price IS NULL);
+
+-- Fails the migration if any auction was left out of bid_backfill_auctions
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM bids
+        WHERE status = 'submitted'
+            AND (settlement_contract IS NULL OR base_price IS NULL)
+    ) THEN
+        RAISE EXCEPTION 'submitted bids without a settlement_contract or base_price remain';
+    END IF;
+END
+$$;
+
+DROP TABLE bid_backfill_auctions;
+
+COMMIT;
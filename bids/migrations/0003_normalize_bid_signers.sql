@@ -0,0 +1,13 @@
+-- Signers used to be stored as the Bid's sender was written, so checksummed
+-- senders kept their case. The API now stores and compares them as lowercase
+-- hex without 0x, like auction_address. Safe to run more than once.
+--
+-- A sender written without 0x lost its first two hex characters, so those
+-- rows can't be recovered here. They are listed by:
+--
+--   SELECT bid_id, signer FROM bids WHERE LENGTH(signer) <> 40;
+BEGIN;
+
+UPDATE bids SET signer = LOWER(signer) WHERE signer <> LOWER(signer);
+
+COMMIT;
@@ -0,0 +1,15 @@
+-- Indexes for the lookups every PUT makes on the signer's other Bids. Run
+-- after 0003, since they index signer as stored. Safe to run more than once.
+--
+-- Built concurrently so Bids can still be stored meanwhile, which can't be
+-- done inside a transaction. If a build fails, drop the invalid index and run
+-- this again.
+
+-- Signer's exposure on a settlement contract
+CREATE INDEX CONCURRENTLY IF NOT EXISTS bids_signer_settlement_contract_submitted_idx
+    ON bids (signer, settlement_contract)
+    WHERE status = 'submitted';
+
+-- Signer's highest nonce in an auction, and the Bids a new one replaces
+CREATE INDEX CONCURRENTLY IF NOT EXISTS bids_signer_auction_idx
+    ON bids (signer, auction_address, auction_name);
@@ -9,6 +9,10 @@ pub struct Auction {
     pub end_block: u64,
     pub settlement_contract: Address,
     pub base_price: U256,
+    /// Overrides the chain's `start_grace_blocks`
+    pub start_grace_blocks: Option<u64>,
+    /// Overrides the chain's `end_margin_blocks`
+    pub end_margin_blocks: Option<u64>,
 }
 
 impl Auction {
@@ -27,6 +31,8 @@ impl Auction {
             end_block,
             settlement_contract,
             base_price,
+            start_grace_blocks: None,
+            end_margin_blocks: None,
         }
     }
 }
@@ -1,6 +1,5 @@
 use chrono::{DateTime, Utc};
 use hex;
-use sha2::{Digest, Sha256};
 
 use crate::{
     auction::Auction,
@@ -11,6 +10,7 @@ use crate::{
 pub struct Bid {
     pub payload: BidPayload,
     pub parsed_values: ParsedValues,
+    pub typed_data_hash: [u8; 32],
     pub auction: Auction,
     pub received_time: DateTime<Utc>,
 }
@@ -19,22 +19,22 @@ impl Bid {
     pub fn new(
         payload: BidPayload,
         parsed_values: ParsedValues,
+        typed_data_hash: [u8; 32],
         received_time: DateTime<Utc>,
         auction: Auction,
     ) -> Bid {
         Bid {
             payload,
             parsed_values,
+            typed_data_hash,
             received_time,
             auction,
         }
     }
 
+    /// The EIP712 hash of the Bid typed data, so the same signed Bid always
+    /// gets the same id and clients can compute it before submitting
     pub fn hash(&self) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(&self.payload.sender);
-        hasher.update(&self.payload.signature);
-        hasher.update(&self.received_time.to_rfc3339());
-        hex::encode(hasher.finalize())
+        hex::encode(self.typed_data_hash)
     }
 }
@@ -1,10 +1,11 @@
+use crate::error::BidError;
 use eip_712::{FieldType, MessageTypes, EIP712};
 use ethers::types::U256;
 use lazy_static::lazy_static;
 use serde;
 use serde::{Deserialize, Serialize};
 use validator::Validate;
-use validator::ValidationErrors;
+use validator::{ValidationError, ValidationErrors};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(deny_unknown_fields)]
@@ -18,9 +19,44 @@ pub struct BidPayload {
 pub struct ParsedValues {
     pub auction_name: String,
     pub auction_address: String,
+    pub bidder: String,
     pub amount: U256,
     pub base_price: U256,
     pub tip: U256,
+    pub deadline: Option<Deadline>,
+    pub nonce: Option<U256>,
+}
+
+/// The last block or unix timestamp (in seconds) a Bid can be included in a
+/// settlement
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum Deadline {
+    Block(u64),
+    Timestamp(u64),
+}
+
+impl Deadline {
+    /// Version 2 Bids sign a deadline block and a deadline timestamp, 0 for
+    /// none, and can set at most one of them
+    fn parse(block: U256, timestamp: U256) -> Result<Option<Deadline>, String> {
+        // Deadlines are stored as BIGINT
+        let parse_u64 = |deadline: U256, field: &str| match deadline > U256::from(i64::MAX) {
+            true => Err(format!("{} parsing error: deadline is too large", field)),
+            false => Ok(deadline.as_u64()),
+        };
+        match (block.is_zero(), timestamp.is_zero()) {
+            (true, true) => Ok(None),
+            (false, true) => Ok(Some(Deadline::Block(parse_u64(block, "deadlineBlock")?))),
+            (true, false) => Ok(Some(Deadline::Timestamp(parse_u64(
+                timestamp,
+                "deadlineTimestamp",
+            )?))),
+            (false, false) => Err(
+                "deadline parsing error: only one of deadlineBlock and deadlineTimestamp can be set"
+                    .to_string(),
+            ),
+        }
+    }
 }
 
 impl ParsedValues {
@@ -84,33 +120,83 @@ lazy_static! {
         );
         types
     };
+    // Version 2 Bids end with a deadline block and timestamp, 0 if there is none
+    static ref EXPECTED_BID_V2_REQUEST_MESSAGE_TYPES: MessageTypes = {
+        let mut types = EXPECTED_BID_REQUEST_MESSAGE_TYPES.clone();
+        if let Some(bid_type) = types.get_mut("Bid") {
+            bid_type.push(FieldType {
+                name: "deadlineBlock".to_string(),
+                r#type: "uint256".to_string(),
+            });
+            bid_type.push(FieldType {
+                name: "deadlineTimestamp".to_string(),
+                r#type: "uint256".to_string(),
+            });
+        }
+        types
+    };
 }
 
 impl Validate for BidPayload {
     fn validate(&self) -> Result<(), ValidationErrors> {
-        if self.typed_data.types != *EXPECTED_BID_REQUEST_MESSAGE_TYPES {
-            return Err(ValidationErrors::new());
+        let mut expected_types = match self.typed_data.domain.version.as_str() {
+            "2" => EXPECTED_BID_V2_REQUEST_MESSAGE_TYPES.clone(),
+            _ => EXPECTED_BID_REQUEST_MESSAGE_TYPES.clone(),
+        };
+        // Either version of the Bid type can end with a nonce
+        if self.has_nonce() {
+            if let Some(bid_type) = expected_types.get_mut("Bid") {
+                bid_type.push(FieldType {
+                    name: "nonce".to_string(),
+                    r#type: "uint256".to_string(),
+                });
+            }
         }
+        validate_typed_data(&self.typed_data, &expected_types, "Bid")
+    }
+}
 
-        // Validate static domain fields
-        if self.typed_data.domain.name != "Pikapool Auction" {
-            return Err(ValidationErrors::new());
-        };
-        if self.typed_data.domain.version != "1" {
-            return Err(ValidationErrors::new());
-        };
+/// Checks the static parts of a Pikapool EIP712 message, naming every field
+/// that doesn't match
+pub fn validate_typed_data(
+    typed_data: &EIP712,
+    expected_types: &MessageTypes,
+    primary_type: &str,
+) -> Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::new();
+    if typed_data.types != *expected_types {
+        errors.add("typed_data.types", ValidationError::new("unexpected_types"));
+    }
 
-        // Validate primary type
-        if self.typed_data.primary_type != "Bid" {
-            return Err(ValidationErrors::new());
-        };
+    // Validate primary type
+    if typed_data.primary_type != primary_type {
+        errors.add(
+            "typed_data.primary_type",
+            ValidationError::new("unexpected_primary_type"),
+        );
+    };
 
-        Ok(())
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
     }
 }
 
 impl BidPayload {
-    pub fn parse_values(&self) -> Result<ParsedValues, String> {
+    pub fn parse_values(&self) -> Result<ParsedValues, BidError> {
+        self.parse_message().map_err(BidError::MalformedPayload)
+    }
+
+    /// Whether the signed Bid type has a nonce, so the nonce in the message
+    /// is covered by the signature
+    fn has_nonce(&self) -> bool {
+        match self.typed_data.types.get("Bid") {
+            Some(fields) => fields.iter().any(|field| field.name == "nonce"),
+            None => false,
+        }
+    }
+
+    fn parse_message(&self) -> Result<ParsedValues, String> {
         //
         // AVERT YOUR EYES
         //
@@ -130,6 +216,11 @@ impl BidPayload {
             .ok_or("auctionAddress parsing error")?
             .as_str()
             .ok_or("auctionAddress parsing error")?;
+        let bidder = message
+            .get("bidder")
+            .ok_or("bidder parsing error")?
+            .as_str()
+            .ok_or("bidder parsing error")?;
         let amount = message
             .get("amount")
             .ok_or("amount parsing error")?
@@ -151,13 +242,47 @@ impl BidPayload {
             .ok_or("tip parsing error")?
             .parse::<U256>()
             .map_err(|e| format!("tip parsing error: {}", e.to_string()))?;
+        let deadline = match self.typed_data.domain.version.as_str() {
+            "2" => Deadline::parse(
+                message
+                    .get("deadlineBlock")
+                    .ok_or("deadlineBlock parsing error")?
+                    .as_str()
+                    .ok_or("deadlineBlock parsing error")?
+                    .parse::<U256>()
+                    .map_err(|e| format!("deadlineBlock parsing error: {}", e))?,
+                message
+                    .get("deadlineTimestamp")
+                    .ok_or("deadlineTimestamp parsing error")?
+                    .as_str()
+                    .ok_or("deadlineTimestamp parsing error")?
+                    .parse::<U256>()
+                    .map_err(|e| format!("deadlineTimestamp parsing error: {}", e))?,
+            )?,
+            _ => None,
+        };
+        let nonce = match self.has_nonce() {
+            true => Some(
+                message
+                    .get("nonce")
+                    .ok_or("nonce parsing error")?
+                    .as_str()
+                    .ok_or("nonce parsing error")?
+                    .parse::<U256>()
+                    .map_err(|e| format!("nonce parsing error: {}", e))?,
+            ),
+            false => None,
+        };
 
         let parsed_values = ParsedValues {
             auction_name: auction_name.to_string(),
             auction_address: auction_address.to_string(),
+            bidder: bidder.to_string(),
             amount,
             base_price,
             tip,
+            deadline,
+            nonce,
         };
         Ok(parsed_values)
     }
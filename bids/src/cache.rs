@@ -1,161 +1,503 @@
+use crate::error::BidError;
 use crate::utils::get_env_var;
 use crate::{auction::Auction, utils::Connectable};
 use async_trait::async_trait;
 use ethers::types::{Address, U256};
 use hex;
-use redis::{Commands, RedisError};
+use redis::aio::ConnectionManager;
+use redis::RedisError;
 use std::str::FromStr;
 
 #[async_trait]
 pub trait Cache: Connectable {
-    fn get_signer_approve_and_bal_amts(
+    async fn get_signer_approve_and_bal_amts(
         &mut self,
         chain_id: &str,
         verifying_contract: &Address,
         signer: &Address,
-    ) -> Result<Option<(U256, U256)>, String>;
-    fn get_auction(
+    ) -> Result<Option<(U256, U256)>, BidError>;
+    async fn get_auction(
         &mut self,
         chain_id: &str,
         auction_contract: &Address,
         auction_name: &str,
-    ) -> Result<Option<Auction>, String>;
-    fn get_synced_block(
+    ) -> Result<Option<Auction>, BidError>;
+    async fn get_synced_block(
         &mut self,
         chain_id: &str,
         settlement_contract: &Address,
-    ) -> Result<u64, String>;
+    ) -> Result<u64, BidError>;
+    async fn get_synced_timestamp(
+        &mut self,
+        chain_id: &str,
+        settlement_contract: &Address,
+    ) -> Result<Option<u64>, BidError>;
+    async fn get_bid_context(
+        &mut self,
+        chain_id: &str,
+        auction_contract: &Address,
+        auction_name: &str,
+        settlement_contract: &Address,
+        signer: &Address,
+    ) -> Result<BidContext, BidError>;
+}
+
+/// Everything needed from the cache to validate a Bid, fetched in one round-trip
+#[derive(Debug, Clone, PartialEq)]
+pub struct BidContext {
+    pub auction: Option<Auction>,
+    pub synced_block: Option<u64>,
+    /// Unix time in seconds chain-state-service last synced at
+    pub synced_timestamp: Option<u64>,
+    pub signer_approve_and_bal_amts: Option<(U256, U256)>,
+}
+
+const AUCTION_FIELDS: [&str; 4] = ["startBlock", "endBlock", "settlementContract", "basePrice"];
+// Set for auctions with their own bidding margins
+const AUCTION_MARGIN_FIELDS: [&str; 2] = ["startGraceBlocks", "endMarginBlocks"];
+const SIGNER_DETAILS_FIELDS: [&str; 2] = ["approveValue", "balanceValue"];
+
+/// Builds the keys chain-state-service writes each cache entry under. Keys of
+/// the current (v2) schema use full lowercase addresses and start with the
+/// configured prefix. In compatibility mode the legacy keys, which only have
+/// the first four hex characters of the settlement contract, are read when the
+/// v2 key is missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySchema {
+    pub prefix: String,
+    pub read_legacy: bool,
+}
+
+impl KeySchema {
+    /// Reads `REDIS_KEY_PREFIX` (default none) and `REDIS_KEY_SCHEMA`, `v2`
+    /// (the default) or `compat` to fall back to legacy keys
+    pub fn from_env() -> Self {
+        KeySchema {
+            prefix: get_env_var("REDIS_KEY_PREFIX").unwrap_or_default(),
+            read_legacy: get_env_var("REDIS_KEY_SCHEMA").as_deref() == Ok("compat"),
+        }
+    }
+
+    /// The keys to read an entry from, in order of preference
+    fn keys(&self, key: String, legacy_key: String) -> Vec<String> {
+        let key = match self.prefix.is_empty() {
+            true => format!("v2:{}", key),
+            false => format!("{}:v2:{}", self.prefix, key),
+        };
+        match self.read_legacy {
+            true => vec![key, legacy_key],
+            false => vec![key],
+        }
+    }
+
+    fn settlement_keys(
+        &self,
+        chain_id: &str,
+        settlement_contract: &Address,
+        name: &str,
+    ) -> Vec<String> {
+        self.keys(
+            format!(
+                "{}:settlement:{}:{}",
+                chain_id,
+                hex_address(settlement_contract),
+                name
+            ),
+            format!(
+                "{}:{}:{}",
+                chain_id,
+                &hex::encode(settlement_contract)[..4],
+                name
+            ),
+        )
+    }
+
+    pub fn synced_block_keys(&self, chain_id: &str, settlement_contract: &Address) -> Vec<String> {
+        self.settlement_keys(chain_id, settlement_contract, "syncedBlock")
+    }
+
+    pub fn synced_timestamp_keys(
+        &self,
+        chain_id: &str,
+        settlement_contract: &Address,
+    ) -> Vec<String> {
+        self.settlement_keys(chain_id, settlement_contract, "syncedTimestamp")
+    }
+
+    pub fn auction_keys(
+        &self,
+        chain_id: &str,
+        auction_contract: &Address,
+        auction_name: &str,
+    ) -> Vec<String> {
+        self.keys(
+            format!(
+                "{}:auction:{}:{}",
+                chain_id,
+                hex_address(auction_contract),
+                auction_name
+            ),
+            format!(
+                "{}:auction:{}:{}",
+                chain_id,
+                hex_address(auction_contract),
+                auction_name
+            ),
+        )
+    }
+
+    pub fn signer_details_keys(
+        &self,
+        chain_id: &str,
+        settlement_contract: &Address,
+        signer: &Address,
+    ) -> Vec<String> {
+        self.keys(
+            format!(
+                "{}:settlement:{}:signer:{}",
+                chain_id,
+                hex_address(settlement_contract),
+                hex_address(signer)
+            ),
+            format!(
+                "{}:{}:{}",
+                chain_id,
+                &hex::encode(settlement_contract)[..4],
+                hex::encode(signer)
+            ),
+        )
+    }
+
+    pub fn contract_wallet_keys(&self, chain_id: &str, wallet: &Address) -> Vec<String> {
+        self.keys(
+            format!("{}:contractWallet:{}", chain_id, hex_address(wallet)),
+            format!("{}:contractWallet:{}", chain_id, hex_address(wallet)),
+        )
+    }
+}
+
+fn hex_address(address: &Address) -> String {
+    "0x".to_string() + &hex::encode(address)
+}
+
+fn malformed(key: &str, field: Option<&str>, value: &str) -> BidError {
+    BidError::MalformedCacheValue {
+        key: key.to_string(),
+        field: field.map(|field| field.to_string()),
+        value: value.to_string(),
+    }
+}
+
+fn parse_u64(key: &str, field: Option<&str>, value: &str) -> Result<u64, BidError> {
+    value
+        .parse::<u64>()
+        .map_err(|_| malformed(key, field, value))
+}
+
+fn parse_u256(key: &str, field: &str, value: &str) -> Result<U256, BidError> {
+    U256::from_dec_str(value).map_err(|_| malformed(key, Some(field), value))
+}
+
+fn parse_auction(
+    auction_contract: &Address,
+    auction_name: &str,
+    hash: Option<HashFields<4>>,
+) -> Result<Option<Auction>, BidError> {
+    let (key, [start_block, end_block, settlement_contract, base_price], margins) = match hash {
+        Some(hash) => hash,
+        None => return Ok(None),
+    };
+    let margin = |index: usize| match margins.get(index).cloned().flatten() {
+        Some(margin) => parse_u64(&key, Some(AUCTION_MARGIN_FIELDS[index]), &margin).map(Some),
+        None => Ok(None),
+    };
+    let settlement_contract = match Address::from_str(&settlement_contract) {
+        Ok(address) => address,
+        Err(_) => {
+            return Err(malformed(
+                &key,
+                Some(AUCTION_FIELDS[2]),
+                &settlement_contract,
+            ))
+        }
+    };
+    let mut auction = Auction::new(
+        *auction_contract,
+        auction_name.to_string(),
+        parse_u64(&key, Some(AUCTION_FIELDS[0]), &start_block)?,
+        parse_u64(&key, Some(AUCTION_FIELDS[1]), &end_block)?,
+        settlement_contract,
+        parse_u256(&key, AUCTION_FIELDS[3], &base_price)?,
+    );
+    auction.start_grace_blocks = margin(0)?;
+    auction.end_margin_blocks = margin(1)?;
+    Ok(Some(auction))
+}
+
+fn parse_signer_approve_and_bal_amts(
+    hash: Option<HashFields<2>>,
+) -> Result<Option<(U256, U256)>, BidError> {
+    let (key, [approve_amt, bal_amt], _) = match hash {
+        Some(hash) => hash,
+        None => return Ok(None),
+    };
+    let approve_amt = if approve_amt == "MAX_INT256" {
+        U256::MAX
+    } else {
+        parse_u256(&key, SIGNER_DETAILS_FIELDS[0], &approve_amt)?
+    };
+    let bal_amt = parse_u256(&key, SIGNER_DETAILS_FIELDS[1], &bal_amt)?;
+    Ok(Some((approve_amt, bal_amt)))
+}
+
+// The key of a hash, its required fields and its optional fields, which may be
+// missing
+type HashFields<const N: usize> = (String, [String; N], Vec<Option<String>>);
+
+// HMGET returns a nil for every missing field, so a missing hash comes back as
+// all nils and a hash with only some nils is missing those fields. Fields past
+// the required `names` are optional and returned as they are.
+fn unwrap_hash_fields<const N: usize>(
+    key: &str,
+    names: &[&str; N],
+    mut fields: Vec<Option<String>>,
+) -> Result<Option<HashFields<N>>, BidError> {
+    if fields.iter().all(|field| field.is_none()) {
+        return Ok(None);
+    }
+    let optional_fields = fields.split_off(N.min(fields.len()));
+    let missing_fields: Vec<String> = names
+        .iter()
+        .zip(&fields)
+        .filter(|(_, field)| field.is_none())
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let fields: Option<Vec<String>> = fields.into_iter().collect();
+    match fields.and_then(|fields| <[String; N]>::try_from(fields).ok()) {
+        Some(fields) => Ok(Some((key.to_string(), fields, optional_fields))),
+        None => Err(BidError::CorruptCacheEntry {
+            key: key.to_string(),
+            missing_fields,
+        }),
+    }
+}
+
+// The key and fields of the first hash found, given the fields read from each
+// of its keys
+fn first_hash_fields<const N: usize>(
+    keys: &[String],
+    names: &[&str; N],
+    fields: Vec<Vec<Option<String>>>,
+) -> Result<Option<HashFields<N>>, BidError> {
+    for (key, fields) in keys.iter().zip(fields) {
+        if let Some(hash) = unwrap_hash_fields(key, names, fields)? {
+            return Ok(Some(hash));
+        }
+    }
+    Ok(None)
+}
+
+// The first number found, e.g. the synced block, given the value read from
+// each of its keys
+fn parse_first_u64(keys: &[String], values: Vec<Option<String>>) -> Result<Option<u64>, BidError> {
+    match keys
+        .iter()
+        .zip(values)
+        .find_map(|(key, value)| Some((key, value?)))
+    {
+        Some((key, value)) => Ok(Some(parse_u64(key, None, &value)?)),
+        None => Ok(None),
+    }
 }
 
 pub struct RedisCache {
-    pub connection: Option<redis::Connection>,
+    pub connection: Option<ConnectionManager>,
+    pub key_schema: KeySchema,
+}
+
+impl RedisCache {
+    /// Reads the fields of a hash stored under any of `keys` in one round-trip,
+    /// followed by any `optional_fields` it has
+    async fn get_hash_fields<const N: usize>(
+        &mut self,
+        keys: &[String],
+        fields: &[&str; N],
+        optional_fields: &[&str],
+    ) -> Result<Option<HashFields<N>>, BidError> {
+        let connection = match self.connection.as_mut() {
+            Some(connection) => connection,
+            None => {
+                return Err(BidError::Cache(
+                    "Failed to get redis connection".to_string(),
+                ))
+            }
+        };
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.hget(key, [&fields[..], optional_fields].concat());
+        }
+        let result: Result<Vec<Vec<Option<String>>>, RedisError> =
+            pipe.query_async(connection).await;
+        match result {
+            Ok(hashes) => first_hash_fields(keys, fields, hashes),
+            Err(e) => Err(BidError::Cache(e.to_string())),
+        }
+    }
 }
 
+#[async_trait]
 impl Cache for RedisCache {
-    fn get_synced_block(
+    async fn get_synced_block(
         &mut self,
         chain_id: &str,
         settlement_contract: &Address,
-    ) -> Result<u64, String> {
+    ) -> Result<u64, BidError> {
+        let keys = self
+            .key_schema
+            .synced_block_keys(chain_id, settlement_contract);
         let connection = match self.connection.as_mut() {
             Some(connection) => connection,
-            None => return Err("Couldn't get redis connection".to_string()),
+            None => return Err(BidError::Cache("Couldn't get redis connection".to_string())),
         };
-        let key = format!(
-            "{}:{}:syncedBlock",
-            chain_id,
-            hex::encode(settlement_contract)[..4].to_lowercase()
-        );
-
-        match connection.get(key) {
-            Ok(block) => Ok(block),
-            Err(err) => Err(err.to_string()),
+
+        let result: Result<Vec<Option<String>>, RedisError> =
+            redis::cmd("MGET").arg(&keys).query_async(connection).await;
+        match result {
+            Ok(synced_blocks) => match parse_first_u64(&keys, synced_blocks)? {
+                Some(synced_block) => Ok(synced_block),
+                None => Err(BidError::Cache(format!(
+                    "Cache entry {} is missing",
+                    keys[0]
+                ))),
+            },
+            Err(err) => Err(BidError::Cache(err.to_string())),
         }
     }
 
-    fn get_auction(
+    async fn get_synced_timestamp(
         &mut self,
         chain_id: &str,
-        auction_contract: &Address,
-        auction_name: &str,
-    ) -> Result<Option<Auction>, String> {
+        settlement_contract: &Address,
+    ) -> Result<Option<u64>, BidError> {
+        let keys = self
+            .key_schema
+            .synced_timestamp_keys(chain_id, settlement_contract);
         let connection = match self.connection.as_mut() {
             Some(connection) => connection,
-            None => return Err("Failed to get redis connection".to_string()),
+            None => return Err(BidError::Cache("Couldn't get redis connection".to_string())),
         };
-        let auction_key = format!(
-            "{}:auction:{}:{}",
-            chain_id,
-            "0x".to_string() + &hex::encode(auction_contract).to_lowercase(),
-            auction_name
-        );
-
-        let result: Result<Option<(u64, u64, String, String)>, RedisError> = connection.hget(
-            &auction_key,
-            &["startBlock", "endBlock", "settlementContract", "basePrice"],
-        );
 
+        let result: Result<Vec<Option<String>>, RedisError> =
+            redis::cmd("MGET").arg(&keys).query_async(connection).await;
         match result {
-            Ok(option) => match option {
-                Some((start_block, end_block, settlement_contract, base_price_string)) => {
-                    let settlement_contract = match Address::from_str(settlement_contract.as_str())
-                    {
-                        Ok(address) => address,
-                        Err(err) => return Err(err.to_string()),
-                    };
-                    let base_price = match U256::from_dec_str(&base_price_string) {
-                        Ok(base_price) => base_price,
-                        Err(err) => return Err(err.to_string()),
-                    };
-                    Ok(Some(Auction::new(
-                        auction_contract.clone(),
-                        auction_name.to_string(),
-                        start_block,
-                        end_block,
-                        settlement_contract,
-                        base_price,
-                    )))
-                }
-                None => Ok(None),
-            },
-            // Response was nil means key doesn't exist -- user not approved
-            Err(e) => {
-                if e.to_string().contains("response was nil") {
-                    Ok(None)
-                } else {
-                    Err(e.to_string())
-                }
-            }
+            Ok(synced_timestamps) => parse_first_u64(&keys, synced_timestamps),
+            Err(err) => Err(BidError::Cache(err.to_string())),
         }
     }
 
-    fn get_signer_approve_and_bal_amts(
+    async fn get_auction(
+        &mut self,
+        chain_id: &str,
+        auction_contract: &Address,
+        auction_name: &str,
+    ) -> Result<Option<Auction>, BidError> {
+        let keys = self
+            .key_schema
+            .auction_keys(chain_id, auction_contract, auction_name);
+        let hash = self
+            .get_hash_fields(&keys, &AUCTION_FIELDS, &AUCTION_MARGIN_FIELDS)
+            .await?;
+        parse_auction(auction_contract, auction_name, hash)
+    }
+
+    async fn get_signer_approve_and_bal_amts(
         &mut self,
         chain_id: &str,
         verifying_contract: &Address,
         signer: &Address,
-    ) -> Result<Option<(U256, U256)>, String> {
-        let signer_details_key = format!(
-            "{}:{}:{}",
-            chain_id,
-            &hex::encode(verifying_contract)[..4].to_lowercase(),
-            &hex::encode(signer).to_lowercase()
-        );
+    ) -> Result<Option<(U256, U256)>, BidError> {
+        let keys = self
+            .key_schema
+            .signer_details_keys(chain_id, verifying_contract, signer);
+        // A missing hash means the signer has not approved
+        let hash = self
+            .get_hash_fields(&keys, &SIGNER_DETAILS_FIELDS, &[])
+            .await?;
+        parse_signer_approve_and_bal_amts(hash)
+    }
+
+    async fn get_bid_context(
+        &mut self,
+        chain_id: &str,
+        auction_contract: &Address,
+        auction_name: &str,
+        settlement_contract: &Address,
+        signer: &Address,
+    ) -> Result<BidContext, BidError> {
+        let auction_keys = self
+            .key_schema
+            .auction_keys(chain_id, auction_contract, auction_name);
+        let synced_block_keys = self
+            .key_schema
+            .synced_block_keys(chain_id, settlement_contract);
+        let synced_timestamp_keys = self
+            .key_schema
+            .synced_timestamp_keys(chain_id, settlement_contract);
+        let signer_details_keys =
+            self.key_schema
+                .signer_details_keys(chain_id, settlement_contract, signer);
         let connection = match self.connection.as_mut() {
             Some(connection) => connection,
-            None => return Err("Failed to get redis connection".to_string()),
+            None => {
+                return Err(BidError::Cache(
+                    "Failed to get redis connection".to_string(),
+                ))
+            }
         };
-        let result: Result<Option<(String, String)>, RedisError> =
-            connection.hget(&signer_details_key, &["approveValue", "balanceValue"]);
 
-        match result {
-            Ok(option) => match option {
-                Some((approve_amt_string, bal_amt_string)) => {
-                    let approve_amt = if approve_amt_string == "MAX_INT256" {
-                        U256::MAX
-                    } else {
-                        match U256::from_dec_str(&approve_amt_string) {
-                            Ok(f) => f,
-                            Err(err) => return Err(err.to_string()),
-                        }
-                    };
-                    let bal_amt = match U256::from_dec_str(&bal_amt_string) {
-                        Ok(bal_amt) => bal_amt,
-                        Err(err) => return Err(err.to_string()),
-                    };
-                    Ok(Some((approve_amt, bal_amt)))
-                }
-                None => Ok(None),
-            },
-            // Response was nil means key doesn't exist -- user not approved
-            Err(e) => {
-                if e.to_string().contains("response was nil") {
-                    Ok(None)
-                } else {
-                    Err(e.to_string())
-                }
-            }
+        // Every reply is a list of optional strings, one per field or key
+        let mut pipe = redis::pipe();
+        for key in &auction_keys {
+            pipe.hget(
+                key,
+                [&AUCTION_FIELDS[..], &AUCTION_MARGIN_FIELDS[..]].concat(),
+            );
         }
+        pipe.cmd("MGET").arg(&synced_block_keys);
+        pipe.cmd("MGET").arg(&synced_timestamp_keys);
+        for key in &signer_details_keys {
+            pipe.hget(key, &SIGNER_DETAILS_FIELDS);
+        }
+        let result: Result<Vec<Vec<Option<String>>>, RedisError> =
+            pipe.query_async(connection).await;
+        let mut replies = match result {
+            Ok(replies) => replies.into_iter(),
+            Err(e) => return Err(BidError::Cache(e.to_string())),
+        };
+        let auction_fields: Vec<_> = replies.by_ref().take(auction_keys.len()).collect();
+        let synced_blocks = replies.next().unwrap_or_default();
+        let synced_timestamps = replies.next().unwrap_or_default();
+        let signer_details_fields: Vec<_> = replies.collect();
+
+        let auction = parse_auction(
+            auction_contract,
+            auction_name,
+            first_hash_fields(&auction_keys, &AUCTION_FIELDS, auction_fields)?,
+        )?;
+        let synced_block = parse_first_u64(&synced_block_keys, synced_blocks)?;
+        let synced_timestamp = parse_first_u64(&synced_timestamp_keys, synced_timestamps)?;
+        let signer_approve_and_bal_amts = parse_signer_approve_and_bal_amts(first_hash_fields(
+            &signer_details_keys,
+            &SIGNER_DETAILS_FIELDS,
+            signer_details_fields,
+        )?)?;
+
+        Ok(BidContext {
+            auction,
+            synced_block,
+            synced_timestamp,
+            signer_approve_and_bal_amts,
+        })
     }
 }
 
@@ -165,37 +507,41 @@ impl Connectable for RedisCache {
         self.connection.is_some()
     }
 
-    async fn ping(&mut self) -> Result<(), String> {
-        let connection = match self.connection.as_mut() {
-            Some(connection) => connection,
-            None => return Err("Failed to get redis connection".to_string()),
-        };
-        match redis::cmd("PING").query::<String>(&mut *connection) {
-            Ok(response) => {
-                if response == "PONG" {
-                    Ok(())
-                } else {
-                    Err("Ping returned unexpected result".to_string())
-                }
-            }
-            Err(e) => return Err(e.to_string()),
+    // The ConnectionManager transparently reconnects when a command fails, so
+    // there is no need to round-trip a PING before reusing it
+    async fn ping(&mut self) -> Result<(), BidError> {
+        match self.connection {
+            Some(_) => Ok(()),
+            None => Err(BidError::Cache(
+                "Failed to get redis connection".to_string(),
+            )),
         }
     }
 
-    async fn connect(&mut self) -> Result<(), String> {
-        let redis_url = get_env_var("REDIS_URL")?;
+    async fn connect(&mut self) -> Result<(), BidError> {
+        let redis_url = get_env_var("REDIS_URL").map_err(BidError::Cache)?;
         let client = match redis::Client::open(redis_url) {
             Ok(client) => client,
-            Err(err) => return Err(err.to_string()),
+            Err(err) => return Err(BidError::Cache(err.to_string())),
         };
-        let connection = match client.get_connection() {
+        let mut connection = match ConnectionManager::new(client).await {
             Ok(connection) => connection,
-            Err(err) => return Err(err.to_string()),
+            Err(err) => return Err(BidError::Cache(err.to_string())),
+        };
+        match redis::cmd("PING")
+            .query_async::<_, String>(&mut connection)
+            .await
+        {
+            Ok(response) => {
+                if response != "PONG" {
+                    return Err(BidError::Cache(
+                        "Ping returned unexpected result".to_string(),
+                    ));
+                }
+            }
+            Err(e) => return Err(BidError::Cache(e.to_string())),
         };
         self.connection = Some(connection);
-        match self.ping().await {
-            Ok(_) => Ok(()),
-            Err(err) => Err(err),
-        }
+        Ok(())
     }
 }
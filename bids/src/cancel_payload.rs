@@ -0,0 +1,130 @@
+use crate::bid_payload::validate_typed_data;
+use crate::error::BidError;
+use eip_712::{FieldType, MessageTypes, EIP712};
+use lazy_static::lazy_static;
+use serde;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use validator::ValidationErrors;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CancelPayload {
+    pub typed_data: EIP712,
+    pub sender: String,
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ParsedCancelValues {
+    pub auction_name: String,
+    pub auction_address: String,
+    pub bidder: String,
+    pub bid_id: String,
+}
+
+lazy_static! {
+    static ref EXPECTED_CANCEL_REQUEST_MESSAGE_TYPES: MessageTypes = {
+        let mut types = MessageTypes::new();
+        types.insert(
+            "EIP712Domain".to_string(),
+            vec![
+                FieldType {
+                    name: "name".to_string(),
+                    r#type: "string".to_string(),
+                },
+                FieldType {
+                    name: "version".to_string(),
+                    r#type: "string".to_string(),
+                },
+                FieldType {
+                    name: "chainId".to_string(),
+                    r#type: "uint256".to_string(),
+                },
+                FieldType {
+                    name: "verifyingContract".to_string(),
+                    r#type: "address".to_string(),
+                },
+            ],
+        );
+        types.insert(
+            "Cancel".to_string(),
+            vec![
+                FieldType {
+                    name: "auctionName".to_string(),
+                    r#type: "string".to_string(),
+                },
+                FieldType {
+                    name: "auctionAddress".to_string(),
+                    r#type: "address".to_string(),
+                },
+                FieldType {
+                    name: "bidder".to_string(),
+                    r#type: "address".to_string(),
+                },
+                FieldType {
+                    name: "bidId".to_string(),
+                    r#type: "bytes32".to_string(),
+                },
+            ],
+        );
+        types
+    };
+}
+
+impl Validate for CancelPayload {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        validate_typed_data(
+            &self.typed_data,
+            &EXPECTED_CANCEL_REQUEST_MESSAGE_TYPES,
+            "Cancel",
+        )
+    }
+}
+
+impl CancelPayload {
+    pub fn parse_values(&self) -> Result<ParsedCancelValues, BidError> {
+        self.parse_message().map_err(BidError::MalformedPayload)
+    }
+
+    fn parse_message(&self) -> Result<ParsedCancelValues, String> {
+        let message = self
+            .typed_data
+            .message
+            .as_object()
+            .ok_or("TypedData message must be an object")?;
+        let auction_name = message
+            .get("auctionName")
+            .ok_or("auctionName parsing error")?
+            .as_str()
+            .ok_or("auctionName parsing error")?;
+        let auction_address = message
+            .get("auctionAddress")
+            .ok_or("auctionAddress parsing error")?
+            .as_str()
+            .ok_or("auctionAddress parsing error")?;
+        let bidder = message
+            .get("bidder")
+            .ok_or("bidder parsing error")?
+            .as_str()
+            .ok_or("bidder parsing error")?;
+        // The id of the Bid being withdrawn, so a signed Cancel can't be
+        // replayed against the signer's later Bids in the same auction
+        let bid_id = message
+            .get("bidId")
+            .ok_or("bidId parsing error")?
+            .as_str()
+            .ok_or("bidId parsing error")?;
+        match bid_id.strip_prefix("0x") {
+            Some(hash) if hash.len() == 64 && hex::decode(hash).is_ok() => (),
+            _ => return Err("bidId parsing error".to_string()),
+        };
+
+        Ok(ParsedCancelValues {
+            auction_name: auction_name.to_string(),
+            auction_address: auction_address.to_string(),
+            bidder: bidder.to_string(),
+            bid_id: bid_id.to_lowercase(),
+        })
+    }
+}
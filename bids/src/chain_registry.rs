@@ -0,0 +1,157 @@
+use crate::auction::Auction;
+use crate::error::BidError;
+use crate::utils::get_env_var;
+use chrono::{DateTime, Utc};
+use eip_712::EIP712;
+use ethers::types::Address;
+use serde::Deserialize;
+use serde_json::from_str;
+use std::collections::HashMap;
+use validator::{ValidationError, ValidationErrors};
+
+// How long chain-state-service may go without syncing a chain
+const DEFAULT_MAX_SYNC_AGE: u64 = 60;
+
+fn default_max_sync_age() -> u64 {
+    DEFAULT_MAX_SYNC_AGE
+}
+
+/// What a chain accepts Bids for
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ChainConfig {
+    pub settlement_contracts: Vec<Address>,
+    pub domain_name: String,
+    pub domain_versions: Vec<String>,
+    /// Seconds since chain-state-service last synced the chain after which
+    /// Bids are refused
+    #[serde(default = "default_max_sync_age")]
+    pub max_sync_age: u64,
+    /// Blocks after an auction starts before Bids are accepted
+    #[serde(default)]
+    pub start_grace_blocks: u64,
+    /// Blocks before an auction ends from which Bids are refused, since they
+    /// can't make it into the settlement bundle in time
+    #[serde(default)]
+    pub end_margin_blocks: u64,
+}
+
+/// Supported chains keyed by decimal chain ID, loaded from the
+/// `CHAIN_REGISTRY` JSON env var, e.g.
+/// `{"1": {"settlement_contracts": ["0x.."], "domain_name": "Pikapool Auction", "domain_versions": ["1"]}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainRegistry {
+    chains: HashMap<String, ChainConfig>,
+}
+
+impl ChainRegistry {
+    pub fn new(chains: HashMap<String, ChainConfig>) -> Self {
+        ChainRegistry { chains }
+    }
+
+    pub fn from_env() -> Result<Self, BidError> {
+        let chains = get_env_var("CHAIN_REGISTRY").map_err(BidError::Config)?;
+        match from_str::<HashMap<String, ChainConfig>>(&chains) {
+            Ok(chains) => Ok(ChainRegistry::new(chains)),
+            Err(e) => Err(BidError::Config(format!("Invalid CHAIN_REGISTRY: {}", e))),
+        }
+    }
+
+    /// Adds an error to `result` for each domain field that doesn't match the
+    /// chain of the typed data. Unsupported chains are left to `check_chain`.
+    pub fn validate_domain(
+        &self,
+        typed_data: &EIP712,
+        result: Result<(), ValidationErrors>,
+    ) -> Result<(), ValidationErrors> {
+        let chain = match self.chains.get(&typed_data.domain.chain_id.to_string()) {
+            Some(chain) => chain,
+            None => return result,
+        };
+        let (mut valid, mut errors) = match result {
+            Ok(()) => (true, ValidationErrors::new()),
+            Err(errors) => (false, errors),
+        };
+        if typed_data.domain.name != chain.domain_name {
+            errors.add(
+                "typed_data.domain.name",
+                ValidationError::new("unexpected_name"),
+            );
+            valid = false;
+        }
+        if !chain.domain_versions.contains(&typed_data.domain.version) {
+            errors.add(
+                "typed_data.domain.version",
+                ValidationError::new("unexpected_version"),
+            );
+            valid = false;
+        }
+        match valid {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Checks the typed data is for a supported chain and one of its
+    /// settlement contracts
+    pub fn check_chain(&self, typed_data: &EIP712) -> Result<(), BidError> {
+        let chain_id = typed_data.domain.chain_id.to_string();
+        let chain = match self.chains.get(&chain_id) {
+            Some(chain) => chain,
+            None => return Err(BidError::UnsupportedChain { chain_id }),
+        };
+        let settlement_contract_bytes: [u8; 20] = typed_data.domain.verifying_contract.into();
+        let settlement_contract = Address::from_slice(&settlement_contract_bytes);
+        match chain.settlement_contracts.contains(&settlement_contract) {
+            true => Ok(()),
+            false => Err(BidError::UnsupportedSettlementContract {
+                chain_id,
+                settlement_contract,
+            }),
+        }
+    }
+
+    /// The start grace and end margin of an auction, in blocks. Its own
+    /// margins take precedence over those of the chain.
+    pub fn bidding_margins(&self, chain_id: &str, auction: &Auction) -> (u64, u64) {
+        let (start_grace_blocks, end_margin_blocks) = match self.chains.get(chain_id) {
+            Some(chain) => (chain.start_grace_blocks, chain.end_margin_blocks),
+            None => (0, 0),
+        };
+        (
+            auction.start_grace_blocks.unwrap_or(start_grace_blocks),
+            auction.end_margin_blocks.unwrap_or(end_margin_blocks),
+        )
+    }
+
+    /// Checks chain-state-service synced the chain recently enough for the
+    /// cached auction window and balances to be trusted. A chain it has never
+    /// reported a sync time for fails the check.
+    pub fn check_sync_age(
+        &self,
+        chain_id: &str,
+        synced_timestamp: Option<u64>,
+        received_time: DateTime<Utc>,
+    ) -> Result<(), BidError> {
+        let max_sync_age = match self.chains.get(chain_id) {
+            Some(chain) => chain.max_sync_age,
+            None => {
+                return Err(BidError::UnsupportedChain {
+                    chain_id: chain_id.to_string(),
+                })
+            }
+        };
+        let received_timestamp = received_time.timestamp() as u64;
+        match synced_timestamp {
+            Some(synced_timestamp)
+                if received_timestamp.saturating_sub(synced_timestamp) <= max_sync_age =>
+            {
+                Ok(())
+            }
+            _ => Err(BidError::ChainStateStale {
+                synced_timestamp,
+                received_timestamp,
+                max_sync_age,
+            }),
+        }
+    }
+}
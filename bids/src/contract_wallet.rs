@@ -0,0 +1,291 @@
+use crate::cache::{KeySchema, RedisCache};
+use crate::error::BidError;
+use crate::utils::{get_env_var, Connectable};
+use async_trait::async_trait;
+use ethers::abi::{encode, Token};
+use ethers::providers::{Http, HttpClientError, Middleware, Provider, ProviderError};
+use ethers::types::{Address, Bytes, TransactionRequest};
+use redis::AsyncCommands;
+use serde_json::from_str;
+use std::collections::HashMap;
+
+// bytes4(keccak256("isValidSignature(bytes32,bytes)"))
+const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Verifies EIP-1271 signatures of contract wallets, which can't sign with
+/// ECDSA themselves
+#[async_trait]
+pub trait ContractWalletVerifier: Connectable {
+    async fn is_contract_wallet(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+    ) -> Result<bool, BidError>;
+    async fn is_valid_signature(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool, BidError>;
+}
+
+/// Calls `isValidSignature` on the wallet through an RPC provider for its chain
+pub struct ProviderVerifier {
+    pub providers: HashMap<String, Provider<Http>>,
+}
+
+impl ProviderVerifier {
+    fn provider(&self, chain_id: &str) -> Result<&Provider<Http>, BidError> {
+        match self.providers.get(chain_id) {
+            Some(provider) => Ok(provider),
+            None => Err(BidError::ContractWalletVerifier(format!(
+                "No provider for chain {}",
+                chain_id
+            ))),
+        }
+    }
+}
+
+/// Whether the node ran the call and it reverted, as opposed to the call never
+/// reaching the node or its response not being understood
+fn is_revert(error: &ProviderError) -> bool {
+    match error {
+        ProviderError::JsonRpcClientError(e) => match e.downcast_ref::<HttpClientError>() {
+            Some(HttpClientError::JsonRpcError(e)) => {
+                e.code == 3 || e.message.to_lowercase().contains("revert")
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl ContractWalletVerifier for ProviderVerifier {
+    async fn is_contract_wallet(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+    ) -> Result<bool, BidError> {
+        // Signers on chains without a provider are treated as ECDSA signers,
+        // so they get the ECDSA error rather than a server error
+        let provider = match self.providers.get(chain_id) {
+            Some(provider) => provider,
+            None => return Ok(false),
+        };
+        match provider.get_code(*wallet, None).await {
+            Ok(code) => Ok(!code.is_empty()),
+            Err(e) => Err(BidError::ContractWalletVerifier(e.to_string())),
+        }
+    }
+
+    async fn is_valid_signature(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool, BidError> {
+        let mut data = EIP1271_MAGIC_VALUE.to_vec();
+        data.extend(encode(&[
+            Token::FixedBytes(hash.to_vec()),
+            Token::Bytes(signature.to_vec()),
+        ]));
+        let call = TransactionRequest::new()
+            .to(*wallet)
+            .data(Bytes::from(data));
+        // Wallets that reject the signature may revert instead of returning
+        // another value
+        match self.provider(chain_id)?.call(&call.into(), None).await {
+            Ok(result) => Ok(result.len() >= 4 && result[..4] == EIP1271_MAGIC_VALUE),
+            Err(e) if is_revert(&e) => Ok(false),
+            Err(e) => Err(BidError::ContractWalletVerifier(e.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Connectable for ProviderVerifier {
+    async fn is_connected(&self) -> bool {
+        !self.providers.is_empty()
+    }
+
+    // Providers send each call as its own HTTP request, so there is no
+    // connection to check before reusing them. An unreachable node only fails
+    // the calls for its own chain.
+    async fn ping(&mut self) -> Result<(), BidError> {
+        match self.providers.is_empty() {
+            false => Ok(()),
+            true => Err(BidError::ContractWalletVerifier(
+                "Failed to get provider".to_string(),
+            )),
+        }
+    }
+
+    /// Sets up a provider for each chain in the `RPC_URLS` JSON env var, e.g.
+    /// `{"1": "https://..", "5": "https://.."}`, without calling any of them
+    async fn connect(&mut self) -> Result<(), BidError> {
+        let rpc_urls = get_env_var("RPC_URLS").map_err(BidError::ContractWalletVerifier)?;
+        let rpc_urls = match from_str::<HashMap<String, String>>(&rpc_urls) {
+            Ok(rpc_urls) => rpc_urls,
+            Err(e) => {
+                return Err(BidError::ContractWalletVerifier(format!(
+                    "Invalid RPC_URLS: {}",
+                    e
+                )))
+            }
+        };
+        let mut providers = HashMap::new();
+        for (chain_id, rpc_url) in rpc_urls {
+            match Provider::<Http>::try_from(rpc_url.as_str()) {
+                Ok(provider) => providers.insert(chain_id, provider),
+                Err(e) => return Err(BidError::ContractWalletVerifier(e.to_string())),
+            };
+        }
+        self.providers = providers;
+        Ok(())
+    }
+}
+
+/// chain-state-service records the message hashes each contract wallet has
+/// signed on-chain (e.g. a Safe `SignMsg`), which `isValidSignature` accepts
+/// with an empty signature
+#[async_trait]
+impl ContractWalletVerifier for RedisCache {
+    async fn is_contract_wallet(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+    ) -> Result<bool, BidError> {
+        let keys = self.key_schema.contract_wallet_keys(chain_id, wallet);
+        let connection = match self.connection.as_mut() {
+            Some(connection) => connection,
+            None => {
+                return Err(BidError::ContractWalletVerifier(
+                    "Failed to get redis connection".to_string(),
+                ))
+            }
+        };
+        // EXISTS counts how many of the keys exist
+        match connection.exists::<_, usize>(&keys).await {
+            Ok(exists) => Ok(exists > 0),
+            Err(e) => Err(BidError::ContractWalletVerifier(e.to_string())),
+        }
+    }
+
+    async fn is_valid_signature(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool, BidError> {
+        if !signature.is_empty() {
+            return Ok(false);
+        }
+        let keys = self.key_schema.contract_wallet_keys(chain_id, wallet);
+        let connection = match self.connection.as_mut() {
+            Some(connection) => connection,
+            None => {
+                return Err(BidError::ContractWalletVerifier(
+                    "Failed to get redis connection".to_string(),
+                ))
+            }
+        };
+        let hash = "0x".to_string() + &hex::encode(hash);
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.sismember(key, &hash);
+        }
+        let result: Result<Vec<bool>, redis::RedisError> = pipe.query_async(connection).await;
+        match result {
+            Ok(is_member) => Ok(is_member.contains(&true)),
+            Err(e) => Err(BidError::ContractWalletVerifier(e.to_string())),
+        }
+    }
+}
+
+/// The configured contract wallet verifier, selected with
+/// `CONTRACT_WALLET_VERIFIER` (`provider` or `cache`, the default)
+pub enum ContractWalletBackend {
+    Provider(ProviderVerifier),
+    Cache(RedisCache),
+}
+
+impl ContractWalletBackend {
+    pub fn from_env() -> Self {
+        match get_env_var("CONTRACT_WALLET_VERIFIER").as_deref() {
+            Ok("provider") => ContractWalletBackend::Provider(ProviderVerifier {
+                providers: HashMap::new(),
+            }),
+            _ => ContractWalletBackend::Cache(RedisCache {
+                connection: None,
+                key_schema: KeySchema::from_env(),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl ContractWalletVerifier for ContractWalletBackend {
+    async fn is_contract_wallet(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+    ) -> Result<bool, BidError> {
+        match self {
+            ContractWalletBackend::Provider(verifier) => {
+                verifier.is_contract_wallet(chain_id, wallet).await
+            }
+            ContractWalletBackend::Cache(verifier) => {
+                verifier.is_contract_wallet(chain_id, wallet).await
+            }
+        }
+    }
+
+    async fn is_valid_signature(
+        &mut self,
+        chain_id: &str,
+        wallet: &Address,
+        hash: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool, BidError> {
+        match self {
+            ContractWalletBackend::Provider(verifier) => {
+                verifier
+                    .is_valid_signature(chain_id, wallet, hash, signature)
+                    .await
+            }
+            ContractWalletBackend::Cache(verifier) => {
+                verifier
+                    .is_valid_signature(chain_id, wallet, hash, signature)
+                    .await
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Connectable for ContractWalletBackend {
+    async fn is_connected(&self) -> bool {
+        match self {
+            ContractWalletBackend::Provider(verifier) => verifier.is_connected().await,
+            ContractWalletBackend::Cache(verifier) => verifier.is_connected().await,
+        }
+    }
+
+    async fn ping(&mut self) -> Result<(), BidError> {
+        match self {
+            ContractWalletBackend::Provider(verifier) => verifier.ping().await,
+            ContractWalletBackend::Cache(verifier) => verifier.ping().await,
+        }
+    }
+
+    async fn connect(&mut self) -> Result<(), BidError> {
+        match self {
+            ContractWalletBackend::Provider(verifier) => verifier.connect().await,
+            ContractWalletBackend::Cache(verifier) => verifier.connect().await,
+        }
+    }
+}
@@ -1,317 +1,1324 @@
 use crate::auction::Auction;
 use crate::bid::Bid;
-use crate::bid_payload::BidPayload;
-use crate::cache::{Cache, RedisCache};
-use crate::database::{Database, RdsProvider};
+use crate::bid_payload::{BidPayload, Deadline};
+use crate::cache::{BidContext, Cache, KeySchema, RedisCache};
+use crate::cancel_payload::CancelPayload;
+use crate::chain_registry::ChainRegistry;
+use crate::contract_wallet::{ContractWalletBackend, ContractWalletVerifier};
+use crate::database::{BidCursor, BidQuery, BidRecord, Database, RdsProvider};
+use crate::error::{BidError, ProblemDetails};
+use crate::publisher::{Publisher, SqsPublisher};
 use crate::signature_validation::verify_signature;
-use crate::utils::{lock_connectable_mutex_safely, Connectable};
+use crate::utils::{get_env_var, Connectable, ConnectionPool, PooledConnection};
+use crate::validation::{CheckOutcome, ValidationReport, BID_CHECKS};
+use chrono::{DateTime, Utc};
 use cid::multihash::{Code, MultihashDigest};
 use cid::Cid;
 use eip_712::hash_structured_data;
-use ethers::types::Address;
+use ethers::types::{Address, U256};
 use lambda_http::http::{Method, StatusCode};
-use lambda_http::{Body, Error, Request, Response};
+use lambda_http::{Body, Error, Request, RequestExt, Response};
 use lazy_static::lazy_static;
 use serde::Serialize;
-use serde_json::from_str;
+use serde_json::{from_str, from_value, Value};
+use std::collections::HashMap;
 use std::str::FromStr;
-use tokio::sync::Mutex;
-use validator::Validate;
+use validator::{Validate, ValidationErrors};
 
-// Store the Cache in a Mutex so we can reuse connections between
-// lambda invocations
+const DEFAULT_POOL_SIZE: usize = 4;
+
+// Keep connections in pools so they are reused between lambda invocations
+// and concurrent requests wait for a free connection
 lazy_static! {
-    static ref REDIS_DATABASE: Mutex<RedisCache> = {
-        let cache = RedisCache { connection: None };
-        Mutex::new(cache)
+    static ref REDIS_DATABASE: ConnectionPool<RedisCache> = {
+        let caches = (0..pool_size("REDIS_POOL_SIZE"))
+            .map(|_| RedisCache {
+                connection: None,
+                key_schema: KeySchema::from_env(),
+            })
+            .collect();
+        ConnectionPool::new(caches)
+    };
+    static ref RDS_PROVIDER: ConnectionPool<RdsProvider> = {
+        let databases = (0..pool_size("RDS_POOL_SIZE"))
+            .map(|_| RdsProvider { client: None })
+            .collect();
+        ConnectionPool::new(databases)
+    };
+    static ref SQS_PUBLISHER: ConnectionPool<SqsPublisher> = {
+        let publishers = (0..pool_size("SQS_POOL_SIZE"))
+            .map(|_| SqsPublisher {
+                client: None,
+                queue_url: None,
+            })
+            .collect();
+        ConnectionPool::new(publishers)
     };
-    static ref RDS_PROVIDER: Mutex<RdsProvider> = {
-        let database = RdsProvider { client: None };
-        Mutex::new(database)
+    static ref CONTRACT_WALLET_VERIFIER: ConnectionPool<ContractWalletBackend> = {
+        let verifiers = (0..pool_size("CONTRACT_WALLET_VERIFIER_POOL_SIZE"))
+            .map(|_| ContractWalletBackend::from_env())
+            .collect();
+        ConnectionPool::new(verifiers)
     };
+    static ref CHAIN_REGISTRY: Result<ChainRegistry, BidError> = ChainRegistry::from_env();
+}
+
+fn pool_size(env_var: &str) -> usize {
+    match get_env_var(env_var).map(|size| size.parse::<usize>()) {
+        Ok(Ok(size)) if size > 0 => size,
+        _ => DEFAULT_POOL_SIZE,
+    }
 }
 
 pub async fn request_handler(event: Request) -> Result<Response<Body>, Error> {
-    let cache_mutex = &REDIS_DATABASE;
+    let cache_pool = &REDIS_DATABASE;
     let db = &RDS_PROVIDER;
+    let publisher = &SQS_PUBLISHER;
+    let verifier = &CONTRACT_WALLET_VERIFIER;
+    // Only requests that check Bids against the registry resolve it, so reads
+    // keep working while it is misconfigured
     match event.method() {
-        &Method::PUT => put_request_handler(event, cache_mutex, db).await,
+        &Method::PUT if is_batch_path(event.uri().path()) => match CHAIN_REGISTRY.as_ref() {
+            Ok(registry) => {
+                batch_put_request_handler(event, registry, cache_pool, db, publisher, verifier)
+                    .await
+            }
+            Err(e) => build_error_response(e),
+        },
+        &Method::PUT => match CHAIN_REGISTRY.as_ref() {
+            Ok(registry) => {
+                put_request_handler(event, registry, cache_pool, db, publisher, verifier).await
+            }
+            Err(e) => build_error_response(e),
+        },
+        &Method::POST if is_validate_path(event.uri().path()) => match CHAIN_REGISTRY.as_ref() {
+            Ok(registry) => {
+                validate_request_handler(event, registry, cache_pool, db, verifier).await
+            }
+            Err(e) => build_error_response(e),
+        },
+        &Method::GET => get_request_handler(event, db).await,
+        &Method::DELETE => match CHAIN_REGISTRY.as_ref() {
+            Ok(registry) => delete_request_handler(event, registry, cache_pool, db).await,
+            Err(e) => build_error_response(e),
+        },
         &Method::OPTIONS => build_response(StatusCode::OK, "OK"),
-        _ => build_response(StatusCode::NOT_IMPLEMENTED, "Method not implemented"),
+        _ => build_error_response(&BidError::MethodNotImplemented),
     }
 }
 
 pub async fn put_request_handler(
     event: Request,
-    cache_mutex: &Mutex<impl Cache>,
-    db_mutex: &Mutex<impl Database>,
+    registry: &ChainRegistry,
+    cache_pool: &ConnectionPool<impl Cache>,
+    db_pool: &ConnectionPool<impl Database>,
+    publisher_pool: &ConnectionPool<impl Publisher>,
+    verifier_pool: &ConnectionPool<impl ContractWalletVerifier>,
 ) -> Result<Response<Body>, Error> {
-    let bid = match parse_and_validate_event(event, cache_mutex).await {
+    let bid = match parse_and_validate_event(
+        event,
+        registry,
+        cache_pool,
+        db_pool,
+        verifier_pool,
+        &mut ValidationReport::default(),
+    )
+    .await
+    {
         Ok(bid_payload) => bid_payload,
-        Err(e) => return e,
+        Err(e) => return build_error_response(&e),
     };
 
     println!("Connecting to DB");
-    let mut db = match lock_connectable_mutex_safely(db_mutex).await {
+    let mut db = match db_pool.get().await {
         Ok(db) => db,
-        Err(e) => return build_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        Err(e) => return build_error_response(&e),
     };
 
     println!("Sending to db...");
-    match db.insert_bid(&bid).await {
-        Ok(hash) => {
+    let stored_bid = match db.insert_bid(&bid).await {
+        Ok(stored_bid) => stored_bid,
+        Err(e) => {
+            eprintln!("Error sending to db: {}", e);
+            return build_error_response(&e);
+        }
+    };
+    drop(db);
+    // A resubmitted Bid that has since been replaced or cancelled must not
+    // reach the mempool again
+    if !stored_bid.is_active() {
+        return build_error_response(&BidError::BidNotActive {
+            status: stored_bid.status,
+        });
+    }
+    let hash = stored_bid.id;
+
+    // Publishing failures are returned to the bidder so they resubmit, which is
+    // safe because inserting an existing bid is a no-op
+    println!("Connecting to publisher");
+    let mut publisher = match publisher_pool.get().await {
+        Ok(publisher) => publisher,
+        Err(e) => return build_error_response(&e),
+    };
+    println!("Publishing bid...");
+    match publisher
+        .publish_bid(&bid, &hash, &compute_cid(&hash))
+        .await
+    {
+        Ok(_) => {
             println!("Done! Returning 200.");
             build_response(StatusCode::OK, &hash)
         }
+        Err(e) => {
+            eprintln!("Error publishing bid: {}", e);
+            build_error_response(&e)
+        }
+    }
+}
+
+const MAX_BATCH_SIZE: usize = 50;
+
+/// The id and cid of a stored Bid, or why it was rejected
+#[derive(Debug, Serialize)]
+struct BatchItemResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ProblemDetails>,
+}
+
+impl BatchItemResult {
+    fn new(result: &Result<String, BidError>) -> Self {
+        match result {
+            Ok(id) => BatchItemResult {
+                id: Some(id.clone()),
+                cid: Some(compute_cid(id)),
+                error: None,
+            },
+            Err(e) => BatchItemResult {
+                id: None,
+                cid: None,
+                error: Some(ProblemDetails::from(e)),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BatchResponseBody {
+    results: Vec<BatchItemResult>,
+}
+
+/// Validates each Bid in the batch independently, stores the valid ones in a
+/// single transaction and publishes them. Results are in the order of the batch.
+pub async fn batch_put_request_handler(
+    event: Request,
+    registry: &ChainRegistry,
+    cache_pool: &ConnectionPool<impl Cache>,
+    db_pool: &ConnectionPool<impl Database>,
+    publisher_pool: &ConnectionPool<impl Publisher>,
+    verifier_pool: &ConnectionPool<impl ContractWalletVerifier>,
+) -> Result<Response<Body>, Error> {
+    println!("Deserializing request body");
+    let items = match event.body() {
+        Body::Text(body) => match from_str::<Vec<Value>>(body) {
+            Ok(items) => items,
+            Err(e) => return build_error_response(&BidError::MalformedPayload(e.to_string())),
+        },
+        _ => return build_error_response(&BidError::RequestBodyMissing),
+    };
+    if items.is_empty() || items.len() > MAX_BATCH_SIZE {
+        return build_error_response(&BidError::InvalidBatchSize {
+            size: items.len(),
+            max: MAX_BATCH_SIZE,
+        });
+    }
+
+    // Each valid Bid is replaced by its index in `bids`
+    let mut bid_contexts = BidContexts::new(cache_pool, db_pool, verifier_pool);
+    let mut bids: Vec<Bid> = Vec::new();
+    let mut validated: Vec<Result<usize, BidError>> = Vec::with_capacity(items.len());
+    for (index, item) in items.into_iter().enumerate() {
+        println!("Validating bid {}", index);
+        let result = match from_value::<BidPayload>(item) {
+            Ok(bid_payload) => {
+                validate_bid_payload(
+                    bid_payload,
+                    registry,
+                    &mut bid_contexts,
+                    &mut ValidationReport::default(),
+                )
+                .await
+            }
+            Err(e) => Err(BidError::MalformedPayload(e.to_string())),
+        };
+        validated.push(result.map(|bid| {
+            bids.push(bid);
+            bids.len() - 1
+        }));
+    }
+    drop(bid_contexts);
+
+    let stored = match bids.is_empty() {
+        true => Vec::new(),
+        false => store_and_publish_bids(&bids, db_pool, publisher_pool).await,
+    };
+    let results = validated
+        .into_iter()
+        .map(|result| match result {
+            Ok(index) => BatchItemResult::new(&stored[index]),
+            Err(e) => BatchItemResult::new(&Err(e)),
+        })
+        .collect();
+    println!("Done! Returning 200.");
+    build_json_response(StatusCode::OK, &BatchResponseBody { results })
+}
+
+/// Inserts the Bids in one transaction, then publishes each one that was
+/// stored. Returns the id of each Bid or why it failed, in order.
+async fn store_and_publish_bids(
+    bids: &[Bid],
+    db_pool: &ConnectionPool<impl Database>,
+    publisher_pool: &ConnectionPool<impl Publisher>,
+) -> Vec<Result<String, BidError>> {
+    println!("Connecting to DB");
+    let stored_bids = match db_pool.get().await {
+        Ok(mut db) => {
+            println!("Sending {} bids to db...", bids.len());
+            db.insert_bids(bids).await
+        }
+        Err(e) => Err(e),
+    };
+    let stored_bids = match stored_bids {
+        Ok(stored_bids) => stored_bids,
         Err(e) => {
             eprintln!("Error sending to db: {}", e);
-            build_response(StatusCode::INTERNAL_SERVER_ERROR, &e)
+            return bids.iter().map(|_| Err(e.clone())).collect();
+        }
+    };
+
+    // As with single Bids, publishing failures are returned so the bidder
+    // resubmits the Bid
+    println!("Connecting to publisher");
+    let mut publisher = match publisher_pool.get().await {
+        Ok(publisher) => publisher,
+        Err(e) => {
+            return stored_bids
+                .into_iter()
+                .map(|stored_bid| stored_bid.and(Err(e.clone())))
+                .collect()
         }
+    };
+    // Copies of a Bid in the same batch share the result of publishing it once
+    let mut published: HashMap<String, Result<String, BidError>> = HashMap::new();
+    let mut results = Vec::with_capacity(stored_bids.len());
+    for (bid, stored_bid) in bids.iter().zip(stored_bids) {
+        let stored_bid = match stored_bid {
+            Ok(stored_bid) => stored_bid,
+            Err(e) => {
+                results.push(Err(e));
+                continue;
+            }
+        };
+        if let Some(result) = published.get(&stored_bid.id) {
+            results.push(result.clone());
+            continue;
+        }
+        let result = if !stored_bid.is_active() {
+            Err(BidError::BidNotActive {
+                status: stored_bid.status,
+            })
+        } else {
+            println!("Publishing bid {}...", stored_bid.id);
+            match publisher
+                .publish_bid(bid, &stored_bid.id, &compute_cid(&stored_bid.id))
+                .await
+            {
+                Ok(_) => Ok(stored_bid.id.clone()),
+                Err(e) => {
+                    eprintln!("Error publishing bid: {}", e);
+                    Err(e)
+                }
+            }
+        };
+        published.insert(stored_bid.id, result.clone());
+        results.push(result);
     }
+    results
 }
 
-pub async fn parse_and_validate_event(
+fn is_batch_path(path: &str) -> bool {
+    path.trim_end_matches('/').ends_with("/bids/batch")
+}
+
+#[derive(Debug, Serialize)]
+struct ValidateResponseBody {
+    valid: bool,
+    id: Option<String>,
+    checks: Vec<CheckOutcome>,
+}
+
+/// Runs every Bid check against the cache and database without persisting or
+/// publishing the Bid, and reports the outcome of each check
+pub async fn validate_request_handler(
+    event: Request,
+    registry: &ChainRegistry,
+    cache_pool: &ConnectionPool<impl Cache>,
+    db_pool: &ConnectionPool<impl Database>,
+    verifier_pool: &ConnectionPool<impl ContractWalletVerifier>,
+) -> Result<Response<Body>, Error> {
+    let mut report = ValidationReport::default();
+    let result = parse_and_validate_event(
+        event,
+        registry,
+        cache_pool,
+        db_pool,
+        verifier_pool,
+        &mut report,
+    )
+    .await;
+    let response_body = match result {
+        Ok(bid) => ValidateResponseBody {
+            valid: true,
+            id: Some("0x".to_string() + &bid.hash()),
+            checks: report.into_outcomes(&BID_CHECKS),
+        },
+        // The checks could not be run, so there is nothing to report
+        Err(
+            e @ (BidError::Cache(_)
+            | BidError::ChainStateStale { .. }
+            | BidError::CorruptCacheEntry { .. }
+            | BidError::MalformedCacheValue { .. }
+            | BidError::Database(_)
+            | BidError::ContractWalletVerifier(_)
+            | BidError::ConnectionPool(_)),
+        ) => return build_error_response(&e),
+        Err(_) => ValidateResponseBody {
+            valid: false,
+            id: None,
+            checks: report.into_outcomes(&BID_CHECKS),
+        },
+    };
+    build_json_response(StatusCode::OK, &response_body)
+}
+
+fn is_validate_path(path: &str) -> bool {
+    path.trim_end_matches('/').ends_with("/bids/validate")
+}
+
+pub async fn delete_request_handler(
     event: Request,
-    cache_mutex: &Mutex<impl Cache + Connectable>,
-) -> Result<Bid, Result<Response<Body>, Error>> {
-    let received_time = chrono::Utc::now();
+    registry: &ChainRegistry,
+    cache_pool: &ConnectionPool<impl Cache>,
+    db_pool: &ConnectionPool<impl Database>,
+) -> Result<Response<Body>, Error> {
+    let (auction, signer_address, bid_id) =
+        match parse_and_validate_cancel_event(event, registry, cache_pool).await {
+            Ok(cancellation) => cancellation,
+            Err(e) => return build_error_response(&e),
+        };
 
+    println!("Connecting to DB");
+    let mut db = match db_pool.get().await {
+        Ok(db) => db,
+        Err(e) => return build_error_response(&e),
+    };
+
+    println!("Cancelling bid...");
+    match db
+        .cancel_bid(&bid_id, &auction.address, &auction.name, &signer_address)
+        .await
+    {
+        Ok(Some(hash)) => {
+            println!("Done! Returning 200.");
+            build_response(StatusCode::OK, &hash)
+        }
+        Ok(None) => build_error_response(&BidError::NoActiveBid),
+        Err(e) => {
+            eprintln!("Error sending to db: {}", e);
+            build_error_response(&e)
+        }
+    }
+}
+
+pub async fn parse_and_validate_cancel_event(
+    event: Request,
+    registry: &ChainRegistry,
+    cache_pool: &ConnectionPool<impl Cache>,
+) -> Result<(Auction, Address, String), BidError> {
+    println!("Deserializing request body");
+    let cancel_payload = match event.body() {
+        Body::Text(body) => from_str::<CancelPayload>(body),
+        _ => return Err(BidError::RequestBodyMissing),
+    };
+    let cancel_payload = match cancel_payload {
+        Ok(payload) => payload,
+        Err(e) => return Err(BidError::MalformedPayload(e.to_string())),
+    };
+    let parsed_cancel_values = cancel_payload.parse_values()?;
+    println!("Validating EIP712 msg");
+    let result = registry.validate_domain(&cancel_payload.typed_data, cancel_payload.validate());
+    if let Err(errors) = result {
+        return Err(invalid_typed_data("Cancel", &errors));
+    };
+    registry.check_chain(&cancel_payload.typed_data)?;
+    println!("Verifying signer address");
+    let signer_address = match Address::from_str(&cancel_payload.sender) {
+        Ok(address) => address,
+        Err(_) => return Err(BidError::InvalidSignerAddress),
+    };
+    match Address::from_str(&parsed_cancel_values.bidder) {
+        Ok(bidder) if bidder == signer_address => (),
+        _ => return Err(BidError::BidderMismatch),
+    };
+    println!("Verifying auction contract address");
+    let auction_contract_address = match Address::from_str(&parsed_cancel_values.auction_address) {
+        Ok(address) => address,
+        Err(_) => return Err(BidError::InvalidAuctionAddress),
+    };
+    println!("Verifying signature");
+    let typed_data_hash_bytes: [u8; 32] =
+        match hash_structured_data(cancel_payload.typed_data.clone()) {
+            Ok(hash) => hash.into(),
+            Err(e) => return Err(BidError::MalformedPayload(e.to_string())),
+        };
+    verify_signature(
+        signer_address,
+        typed_data_hash_bytes,
+        &cancel_payload.signature,
+    )?;
+
+    println!("Connecting to Cache");
+    let mut cache = cache_pool.get().await?;
+
+    // Bids can only be withdrawn while the auction is open, since a settlement
+    // bundle may already include them once it has ended
+    let chain_id = cancel_payload.typed_data.domain.chain_id.to_string();
+    let settlement_contract_bytes: [u8; 20] =
+        cancel_payload.typed_data.domain.verifying_contract.into();
+    let settlement_contract = Address::from_slice(&settlement_contract_bytes);
+    println!("Checking chain state is up to date");
+    let synced_timestamp = cache
+        .get_synced_timestamp(&chain_id, &settlement_contract)
+        .await?;
+    registry.check_sync_age(&chain_id, synced_timestamp, Utc::now())?;
+    println!("Checking auction is valid");
+    let auction = match cache
+        .get_auction(
+            &chain_id,
+            &auction_contract_address,
+            &parsed_cancel_values.auction_name,
+        )
+        .await?
+    {
+        Some(auction) => auction,
+        None => {
+            return Err(BidError::AuctionNotFound {
+                auction_address: auction_contract_address,
+            })
+        }
+    };
+    if auction.settlement_contract != settlement_contract {
+        return Err(BidError::SettlementContractMismatch {
+            auction_address: auction.address,
+            expected: auction.settlement_contract,
+            provided: settlement_contract,
+        });
+    }
+    println!("Checking auction has not ended");
+    let cur_synced_block = cache
+        .get_synced_block(&chain_id, &settlement_contract)
+        .await?;
+    if cur_synced_block > auction.end_block {
+        return Err(BidError::AuctionEnded {
+            auction_address: auction.address,
+            end_block: auction.end_block,
+            synced_block: cur_synced_block,
+        });
+    }
+
+    println!("Valid!");
+    Ok((auction, signer_address, parsed_cancel_values.bid_id))
+}
+
+const DEFAULT_LIST_LIMIT: u32 = 20;
+const MAX_LIST_LIMIT: u32 = 100;
+
+pub async fn get_request_handler(
+    event: Request,
+    db_pool: &ConnectionPool<impl Database>,
+) -> Result<Response<Body>, Error> {
+    if event.uri().path().trim_end_matches('/').ends_with("/bids") {
+        return list_request_handler(event, db_pool).await;
+    }
+
+    let bid_id = match parse_bid_id_from_path(event.uri().path()) {
+        Some(bid_id) => bid_id,
+        None => return build_error_response(&BidError::InvalidBidId),
+    };
+
+    println!("Connecting to DB");
+    let mut db = match db_pool.get().await {
+        Ok(db) => db,
+        Err(e) => return build_error_response(&e),
+    };
+
+    println!("Fetching bid {}", bid_id);
+    match db.get_bid(&bid_id).await {
+        Ok(Some(bid_record)) => build_json_response(StatusCode::OK, &bid_record),
+        Ok(None) => build_error_response(&BidError::BidNotFound),
+        Err(e) => {
+            eprintln!("Error fetching from db: {}", e);
+            build_error_response(&e)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BidListResponseBody {
+    bids: Vec<BidRecord>,
+    next_cursor: Option<String>,
+}
+
+pub async fn list_request_handler(
+    event: Request,
+    db_pool: &ConnectionPool<impl Database>,
+) -> Result<Response<Body>, Error> {
+    let query = match parse_bid_query(&event) {
+        Ok(query) => query,
+        Err(e) => return build_error_response(&e),
+    };
+
+    println!("Connecting to DB");
+    let mut db = match db_pool.get().await {
+        Ok(db) => db,
+        Err(e) => return build_error_response(&e),
+    };
+
+    // Fetch one extra bid to find out whether there is another page
+    println!("Listing bids for signer {}", query.signer);
+    let page_query = BidQuery {
+        limit: query.limit + 1,
+        ..query.clone()
+    };
+    let mut bids = match db.list_bids(&page_query).await {
+        Ok(bids) => bids,
+        Err(e) => {
+            eprintln!("Error fetching from db: {}", e);
+            return build_error_response(&e);
+        }
+    };
+    let next_cursor = if bids.len() > query.limit as usize {
+        bids.truncate(query.limit as usize);
+        bids.last()
+            .map(|bid_record| BidCursor::from_record(bid_record).to_string())
+    } else {
+        None
+    };
+    build_json_response(StatusCode::OK, &BidListResponseBody { bids, next_cursor })
+}
+
+fn parse_bid_query(event: &Request) -> Result<BidQuery, BidError> {
+    let params = event.query_string_parameters();
+    let signer = match params.first("signer") {
+        Some(signer) => Address::from_str(signer).map_err(|_| BidError::InvalidSignerAddress)?,
+        None => {
+            return Err(BidError::InvalidQuery(
+                "signer query parameter is required".to_string(),
+            ))
+        }
+    };
+    let auction_address = match params.first("auction") {
+        Some(auction) => {
+            Some(Address::from_str(auction).map_err(|_| BidError::InvalidAuctionAddress)?)
+        }
+        None => None,
+    };
+    let cursor = match params.first("cursor") {
+        Some(cursor) => Some(BidCursor::from_str(cursor).map_err(BidError::InvalidQuery)?),
+        None => None,
+    };
+    let limit = match params.first("limit") {
+        Some(limit) => match limit.parse::<u32>() {
+            Ok(limit) if limit > 0 && limit <= MAX_LIST_LIMIT => limit,
+            _ => {
+                return Err(BidError::InvalidQuery(format!(
+                    "limit must be between 1 and {}",
+                    MAX_LIST_LIMIT
+                )))
+            }
+        },
+        None => DEFAULT_LIST_LIMIT,
+    };
+    Ok(BidQuery {
+        signer,
+        auction_address,
+        status: params.first("status").map(|status| status.to_string()),
+        cursor,
+        limit,
+    })
+}
+
+// Extracts the bid id from a `/v0/bids/{id}` path, normalized to lowercase 0x-prefixed hex
+fn parse_bid_id_from_path(path: &str) -> Option<String> {
+    let mut segments = path.trim_end_matches('/').rsplit('/');
+    let bid_id = segments.next()?;
+    if segments.next()? != "bids" {
+        return None;
+    }
+    let bid_id = bid_id.strip_prefix("0x").unwrap_or(bid_id);
+    if bid_id.len() != 64 || hex::decode(bid_id).is_err() {
+        return None;
+    }
+    Some("0x".to_string() + &bid_id.to_lowercase())
+}
+
+pub async fn parse_and_validate_event(
+    event: Request,
+    registry: &ChainRegistry,
+    cache_pool: &ConnectionPool<impl Cache + Connectable>,
+    db_pool: &ConnectionPool<impl Database>,
+    verifier_pool: &ConnectionPool<impl ContractWalletVerifier>,
+    report: &mut ValidationReport,
+) -> Result<Bid, BidError> {
     // Deserialize the request body into a `BidPayload` struct
     println!("Deserializing request body");
     let bid_payload = match event.body() {
         Body::Text(body) => from_str::<BidPayload>(&body),
-        _ => {
-            return Err(build_response(
-                StatusCode::BAD_REQUEST,
-                "Request body missing",
-            ))
-        }
+        _ => return Err(report.fail("schema", BidError::RequestBodyMissing)),
     };
     // Unwrap the EIP712 struct
     println!("Unwrapping EIP712 struct");
     let bid_payload = match bid_payload {
         Ok(payload) => payload,
-        Err(e) => return Err(build_response(StatusCode::BAD_REQUEST, &e.to_string())),
+        Err(e) => return Err(report.fail("schema", BidError::MalformedPayload(e.to_string()))),
     };
+    validate_bid_payload(
+        bid_payload,
+        registry,
+        &mut BidContexts::new(cache_pool, db_pool, verifier_pool),
+        report,
+    )
+    .await
+}
+
+/// Fetches what validating each Bid needs over a single connection to each
+/// backend, reusing the cache context for Bids on the same auction by the
+/// same signer. Connections are always taken in the order verifier, cache,
+/// database, so concurrent requests can't each hold one the other waits for.
+struct BidContexts<'a, C, D, V> {
+    cache_pool: &'a ConnectionPool<C>,
+    cache: Option<PooledConnection<'a, C>>,
+    contexts: HashMap<(String, Address, String, Address, Address), BidContext>,
+    db_pool: &'a ConnectionPool<D>,
+    db: Option<PooledConnection<'a, D>>,
+    // Cost of the Bids validated so far, which are not stored yet, keyed by
+    // signer, settlement contract, auction and auction name
+    pending: HashMap<(Address, Address, Address, String), U256>,
+    // Highest nonce of the Bids validated so far, keyed by signer, auction
+    // and auction name
+    pending_nonces: HashMap<(Address, Address, String), U256>,
+    verifier_pool: &'a ConnectionPool<V>,
+    verifier: Option<PooledConnection<'a, V>>,
+}
+
+impl<'a, C: Cache, D: Database, V: ContractWalletVerifier> BidContexts<'a, C, D, V> {
+    fn new(
+        cache_pool: &'a ConnectionPool<C>,
+        db_pool: &'a ConnectionPool<D>,
+        verifier_pool: &'a ConnectionPool<V>,
+    ) -> Self {
+        BidContexts {
+            cache_pool,
+            cache: None,
+            contexts: HashMap::new(),
+            db_pool,
+            db: None,
+            pending: HashMap::new(),
+            pending_nonces: HashMap::new(),
+            verifier_pool,
+            verifier: None,
+        }
+    }
+
+    /// Falls back to EIP-1271 when the signature isn't a valid ECDSA
+    /// signature by the signer, which is the case for contract wallets.
+    /// Signers that aren't contract wallets get the ECDSA error. Returns the
+    /// signature as 0x-prefixed hex, which may be empty.
+    async fn verify_contract_wallet_signature(
+        &mut self,
+        chain_id: &str,
+        signer: &Address,
+        hash: [u8; 32],
+        signature: &str,
+        ecdsa_error: BidError,
+    ) -> Result<String, BidError> {
+        let signature = match hex::decode(signature.strip_prefix("0x").unwrap_or(signature)) {
+            Ok(signature) => signature,
+            Err(_) => return Err(ecdsa_error),
+        };
+        let verifier = match self.verifier.as_mut() {
+            Some(verifier) => verifier,
+            None => {
+                // Earlier Bids in a batch may hold the later connections, which
+                // are taken again as needed
+                self.cache = None;
+                self.db = None;
+                println!("Connecting to contract wallet verifier");
+                self.verifier.insert(self.verifier_pool.get().await?)
+            }
+        };
+        println!("Checking whether signer is a contract wallet");
+        if !verifier.is_contract_wallet(chain_id, signer).await? {
+            return Err(ecdsa_error);
+        }
+        println!("Verifying EIP-1271 signature");
+        match verifier
+            .is_valid_signature(chain_id, signer, hash, &signature)
+            .await?
+        {
+            true => Ok(format!("0x{}", hex::encode(signature))),
+            false => Err(BidError::SignatureMismatch),
+        }
+    }
+
+    async fn get(
+        &mut self,
+        chain_id: &str,
+        auction_contract: &Address,
+        auction_name: &str,
+        settlement_contract: &Address,
+        signer: &Address,
+    ) -> Result<BidContext, BidError> {
+        let key = (
+            chain_id.to_string(),
+            *auction_contract,
+            auction_name.to_string(),
+            *settlement_contract,
+            *signer,
+        );
+        if let Some(bid_context) = self.contexts.get(&key) {
+            return Ok(bid_context.clone());
+        }
+        let cache = match self.cache.as_mut() {
+            Some(cache) => cache,
+            None => {
+                println!("Connecting to Cache");
+                self.cache.insert(self.cache_pool.get().await?)
+            }
+        };
+        // Fetch the auction, synced block and signer balances in one round-trip
+        println!("Getting bid context");
+        let bid_context = cache
+            .get_bid_context(
+                chain_id,
+                auction_contract,
+                auction_name,
+                settlement_contract,
+                signer,
+            )
+            .await?;
+        self.contexts.insert(key, bid_context.clone());
+        Ok(bid_context)
+    }
+
+    /// Total cost of the signer's other Bids on the settlement contract,
+    /// including Bids validated earlier in the same batch
+    async fn get_exposure(
+        &mut self,
+        signer: &Address,
+        settlement_contract: &Address,
+        auction_contract: &Address,
+        auction_name: &str,
+    ) -> Result<U256, BidError> {
+        let db = match self.db.as_mut() {
+            Some(db) => db,
+            None => {
+                println!("Connecting to DB");
+                self.db.insert(self.db_pool.get().await?)
+            }
+        };
+        println!("Getting signer exposure");
+        let exposure = db
+            .get_signer_exposure(signer, settlement_contract, auction_contract, auction_name)
+            .await?;
+        let pending = self
+            .pending
+            .iter()
+            .filter(
+                |(
+                    (pending_signer, pending_settlement_contract, pending_auction, pending_name),
+                    _,
+                )| {
+                    pending_signer == signer
+                        && pending_settlement_contract == settlement_contract
+                        && !(pending_auction == auction_contract && pending_name == auction_name)
+                },
+            )
+            .fold(U256::zero(), |total, (_, cost)| total.saturating_add(*cost));
+        Ok(exposure.saturating_add(pending))
+    }
+
+    /// Counts a valid Bid towards the exposure of later Bids, replacing the
+    /// signer's earlier Bid in the same auction
+    fn add_pending(
+        &mut self,
+        signer: Address,
+        settlement_contract: Address,
+        auction_contract: Address,
+        auction_name: String,
+        bid_cost: U256,
+    ) {
+        self.pending.insert(
+            (signer, settlement_contract, auction_contract, auction_name),
+            bid_cost,
+        );
+    }
+
+    /// Highest nonce of the signer's other Bids in the auction, including
+    /// Bids validated earlier in the same batch
+    async fn get_highest_nonce(
+        &mut self,
+        signer: &Address,
+        auction_contract: &Address,
+        auction_name: &str,
+        bid_id: &str,
+    ) -> Result<Option<U256>, BidError> {
+        let db = match self.db.as_mut() {
+            Some(db) => db,
+            None => {
+                println!("Connecting to DB");
+                self.db.insert(self.db_pool.get().await?)
+            }
+        };
+        println!("Getting signer's highest nonce");
+        let highest = db
+            .get_highest_nonce(signer, auction_contract, auction_name, bid_id)
+            .await?;
+        let pending = self
+            .pending_nonces
+            .get(&(*signer, *auction_contract, auction_name.to_string()))
+            .copied();
+        Ok(highest.max(pending))
+    }
+
+    fn add_pending_nonce(
+        &mut self,
+        signer: Address,
+        auction_contract: Address,
+        auction_name: String,
+        nonce: U256,
+    ) {
+        self.pending_nonces
+            .insert((signer, auction_contract, auction_name), nonce);
+    }
+}
+
+async fn validate_bid_payload<C: Cache, D: Database, V: ContractWalletVerifier>(
+    mut bid_payload: BidPayload,
+    registry: &ChainRegistry,
+    bid_contexts: &mut BidContexts<'_, C, D, V>,
+    report: &mut ValidationReport,
+) -> Result<Bid, BidError> {
+    let received_time = Utc::now();
+
     let parsed_bid_values = match bid_payload.parse_values() {
         Ok(parsed_bid_values) => parsed_bid_values,
-        Err(e) => return Err(build_response(StatusCode::BAD_REQUEST, &e.to_string())),
+        Err(e) => return Err(report.fail("schema", e)),
     };
     // Validate the EIP712 msg is a valid Bid
     println!("Validating EIP712 msg");
-    match bid_payload.validate() {
-        Err(_) => {
-            return Err(build_response(
-                StatusCode::BAD_REQUEST,
-                "typed_data is not a valid Pikapool Bid",
-            ))
-        }
-        _ => (),
+    match registry.validate_domain(&bid_payload.typed_data, bid_payload.validate()) {
+        Err(errors) => return Err(report.fail("schema", invalid_typed_data("Bid", &errors))),
+        _ => report.pass("schema"),
+    };
+    // Reject Bids for other chains before anything is fetched for them
+    println!("Verifying chain is supported");
+    match registry.check_chain(&bid_payload.typed_data) {
+        Err(e) => return Err(report.fail("chain", e)),
+        _ => report.pass("chain"),
     };
     // Verify signer address
     println!("Verifying signer address");
-    let signer_address = match Address::from_str(&bid_payload.sender) {
-        Ok(address) => address,
-        Err(_) => {
-            return Err(build_response(
-                StatusCode::BAD_REQUEST,
-                "Invalid signer address",
-            ))
-        }
-    };
-    println!("Signer address: {}", signer_address);
+    let signer_address = report.check(
+        "signer",
+        Address::from_str(&bid_payload.sender).map_err(|_| BidError::InvalidSignerAddress),
+    );
     // Verify auction contract address is a valid Address
     println!("Verifying auction contract address");
-    let auction_contract_address = match Address::from_str(&parsed_bid_values.auction_address) {
-        Ok(address) => address,
-        Err(_) => {
-            return Err(build_response(
-                StatusCode::BAD_REQUEST,
-                "Invalid auction contract address",
-            ))
+    let auction_contract_address = report.check(
+        "auction_address",
+        Address::from_str(&parsed_bid_values.auction_address)
+            .map_err(|_| BidError::InvalidAuctionAddress),
+    );
+    // Verify the signature, which can only be hashed once its addresses are valid
+    let mut typed_data_hash_bytes = None;
+    if let (Some(signer_address), Some(_)) = (signer_address, auction_contract_address) {
+        println!("Verifying signature");
+        let hash_bytes: [u8; 32] = match hash_structured_data(bid_payload.typed_data.clone()) {
+            Ok(hash) => hash.into(),
+            Err(e) => {
+                return Err(report.fail("signature", BidError::MalformedPayload(e.to_string())))
+            }
+        };
+        let signature = match verify_signature(signer_address, hash_bytes, &bid_payload.signature) {
+            // Store the 65-byte form of compact signatures
+            Ok(signature) => Ok(format!("0x{}", signature)),
+            Err(e) => {
+                bid_contexts
+                    .verify_contract_wallet_signature(
+                        &bid_payload.typed_data.domain.chain_id.to_string(),
+                        &signer_address,
+                        hash_bytes,
+                        &bid_payload.signature,
+                        e,
+                    )
+                    .await
+            }
+        };
+        // Stored signatures are always 0x-prefixed hex
+        if let Some(signature) = report.check("signature", signature) {
+            bid_payload.signature = signature;
         }
-    };
-    println!("Auction contract address: {}", auction_contract_address);
-    // Verify the signature
-    println!("Verifying signature");
-    let typed_data_hash_bytes: [u8; 32] = match hash_structured_data(bid_payload.typed_data.clone())
+        typed_data_hash_bytes = Some(hash_bytes);
+    }
+    // The signature only proves the sender signed the message, so the bidder
+    // named in it must be the sender too
+    if let Some(signer_address) = signer_address {
+        println!("Verifying bidder matches signer");
+        report.check(
+            "bidder",
+            match Address::from_str(&parsed_bid_values.bidder) {
+                Ok(bidder) if bidder == signer_address => Ok(()),
+                _ => Err(BidError::BidderMismatch),
+            },
+        );
+    }
+    report.finish()?;
+    let signer_address = signer_address.ok_or(BidError::InvalidSignerAddress)?;
+    let auction_contract_address =
+        auction_contract_address.ok_or(BidError::InvalidAuctionAddress)?;
+    let typed_data_hash_bytes = typed_data_hash_bytes.ok_or(BidError::InvalidSignature)?;
+
+    // Passed in-memory validation, now fetch what the remaining checks need
+    let settlement_contract_bytes: [u8; 20] =
+        bid_payload.typed_data.domain.verifying_contract.into();
+    let settlement_contract = Address::from_slice(&settlement_contract_bytes);
+    let bid_context = match bid_contexts
+        .get(
+            &bid_payload.typed_data.domain.chain_id.to_string(),
+            &auction_contract_address,
+            &parsed_bid_values.auction_name,
+            &settlement_contract,
+            &signer_address,
+        )
+        .await
     {
-        Ok(hash) => hash.into(),
-        Err(e) => return Err(build_response(StatusCode::BAD_REQUEST, &e.to_string())),
+        Ok(bid_context) => bid_context,
+        Err(e) => return Err(report.fail("auction", e)),
     };
-    match verify_signature(
-        signer_address,
-        typed_data_hash_bytes,
-        &bid_payload.signature,
+    // Everything read from the cache is only as current as the last sync, so a
+    // missing auction may just not have been synced yet
+    println!("Checking chain state is up to date");
+    if let Err(e) = registry.check_sync_age(
+        &bid_payload.typed_data.domain.chain_id.to_string(),
+        bid_context.synced_timestamp,
+        received_time,
     ) {
-        Ok(signature) => signature,
-        Err(e) => return Err(build_response(StatusCode::BAD_REQUEST, &e.to_string())),
-    };
-
-    // Passed in-memory validation, now connect to DB
-    println!("Connecting to Cache");
-    let mut cache = match lock_connectable_mutex_safely(cache_mutex).await {
-        Ok(cache) => cache,
-        Err(e) => {
-            return Err(build_response(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &e.to_string(),
-            ))
-        }
-    };
+        return Err(report.fail("auction", e));
+    }
 
     // Check auction is valid
     println!("Checking auction is valid");
-    let auction: Auction = match cache.get_auction(
-        &bid_payload.typed_data.domain.chain_id.to_string(),
-        &auction_contract_address,
-        &parsed_bid_values.auction_name,
-    ) {
-        Ok(a) => match a {
-            Some(option) => option,
-            None => {
-                return Err(build_response(
-                    StatusCode::BAD_REQUEST,
-                    "Specified auction does not exist",
-                ))
-            }
-        },
-        Err(e) => {
-            return Err(build_response(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &e.to_string(),
+    let auction: Auction = match bid_context.auction {
+        Some(auction) => auction,
+        None => {
+            return Err(report.fail(
+                "auction",
+                BidError::AuctionNotFound {
+                    auction_address: auction_contract_address,
+                },
             ))
         }
     };
     // Check user specified settlement contract matches actual settlement contract
     println!("Checking settlement contract matches");
-    let settlement_contract_bytes: [u8; 20] =
-        bid_payload.typed_data.domain.verifying_contract.into();
-    let settlement_contract = Address::from_slice(&settlement_contract_bytes);
     if auction.settlement_contract != settlement_contract {
-        return Err(build_response(
-            StatusCode::BAD_REQUEST,
-            "Specified settlement contract does not match auction settlement contract",
+        return Err(report.fail(
+            "auction",
+            BidError::SettlementContractMismatch {
+                auction_address: auction.address,
+                expected: auction.settlement_contract,
+                provided: settlement_contract,
+            },
         ));
     }
+    report.pass("auction");
+    // The remaining checks don't depend on each other
     // Check user specified base_price matches actual base_price
     println!("Checking base_price matches");
-    if auction.base_price != parsed_bid_values.base_price {
-        return Err(build_response(
-            StatusCode::BAD_REQUEST,
-            "Specified base_price does not match auction base_price",
-        ));
-    }
-    // Check user specified base_price matches actual base_price
-    println!("Getting current block");
-    let cur_synced_block = match cache.get_synced_block(
-        &bid_payload.typed_data.domain.chain_id.to_string(),
-        &settlement_contract,
-    ) {
-        Ok(block) => block,
-        Err(e) => {
-            return Err(build_response(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &e.to_string(),
-            ))
-        }
+    report.check(
+        "base_price",
+        match auction.base_price == parsed_bid_values.base_price {
+            true => Ok(()),
+            false => Err(BidError::BasePriceMismatch {
+                auction_address: auction.address,
+                expected: auction.base_price,
+                provided: parsed_bid_values.base_price,
+            }),
+        },
+    );
+    // Check that the auction has started and has not ended
+    println!("Checking auction is live");
+    report.check(
+        "auction_window",
+        check_auction_window(
+            &auction,
+            bid_context.synced_block,
+            registry.bidding_margins(
+                &bid_payload.typed_data.domain.chain_id.to_string(),
+                &auction,
+            ),
+        ),
+    );
+    // Check the Bid has not expired
+    println!("Checking deadline has not passed");
+    report.check(
+        "deadline",
+        check_deadline(
+            parsed_bid_values.deadline,
+            bid_context.synced_block,
+            received_time,
+        ),
+    );
+
+    // A nonce must be above the signer's other nonces in the auction, so an
+    // older Bid can't take the place of a newer one. A Bid without a nonce
+    // counts as nonce 0, so it can't take the place of one with a nonce either
+    println!("Checking nonce is above signer's highest nonce");
+    let nonce = parsed_bid_values.nonce.unwrap_or_default();
+    let nonce = match bid_contexts
+        .get_highest_nonce(
+            &signer_address,
+            &auction_contract_address,
+            &parsed_bid_values.auction_name,
+            &hex::encode(typed_data_hash_bytes),
+        )
+        .await
+    {
+        Ok(Some(highest)) if nonce <= highest => Err(BidError::StaleNonce { nonce, highest }),
+        Ok(_) => Ok(()),
+        Err(e) => return Err(report.fail("nonce", e)),
     };
-    // Check that the auction has started
-    println!("Checking auction has started");
-    if cur_synced_block < auction.start_block {
-        return Err(build_response(
-            StatusCode::BAD_REQUEST,
-            "Auction has not started",
-        ));
-    }
-    // Check that the auction has not ended
-    println!("Checking auction has not ended");
-    if cur_synced_block > auction.end_block {
-        return Err(build_response(StatusCode::BAD_REQUEST, "Auction has ended"));
-    }
+    report.check("nonce", nonce);
 
     // Check user approval and balance
     println!("Getting user approval and balance");
-    let (signer_approve_amt, signer_bal) = match cache.get_signer_approve_and_bal_amts(
-        &bid_payload.typed_data.domain.chain_id.to_string(),
-        &settlement_contract,
-        &signer_address,
-    ) {
-        Ok(res) => match res {
-            Some(option) => option,
-            None => {
-                return Err(build_response(
-                    StatusCode::FORBIDDEN,
-                    "Signer has not approved the settlement contract",
-                ))
-            }
-        },
-        Err(e) => {
-            return Err(build_response(
-                StatusCode::INTERNAL_SERVER_ERROR,
-                &e.to_string(),
-            ))
-        }
-    };
-    // Verify user approval
-    println!("Verifying user approval");
     let bid_cost = parsed_bid_values.get_bid_cost();
-    if signer_approve_amt < bid_cost {
-        return Err(build_response(
-            StatusCode::FORBIDDEN,
-            &"Signer approval amount is insufficient",
-        ));
-    }
-    // Verify user balance
-    println!("Verifying user balance");
-    if signer_bal < bid_cost {
-        return Err(build_response(
-            StatusCode::FORBIDDEN,
-            &"Signer token balance is insufficient",
-        ));
+    match bid_context.signer_approve_and_bal_amts {
+        Some((signer_approve_amt, signer_bal)) => {
+            // The signer's other open Bids draw on the same approval and balance
+            let committed = match bid_contexts
+                .get_exposure(
+                    &signer_address,
+                    &settlement_contract,
+                    &auction_contract_address,
+                    &parsed_bid_values.auction_name,
+                )
+                .await
+            {
+                Ok(committed) => committed,
+                Err(e) => return Err(report.fail("approval", e)),
+            };
+            let required = committed.saturating_add(bid_cost);
+            // Verify user approval
+            println!("Verifying user approval");
+            report.check(
+                "approval",
+                match signer_approve_amt < required {
+                    true => Err(BidError::InsufficientApproval {
+                        required,
+                        committed,
+                        available: signer_approve_amt,
+                    }),
+                    false => Ok(()),
+                },
+            );
+            // Verify user balance
+            println!("Verifying user balance");
+            report.check(
+                "balance",
+                match signer_bal < required {
+                    true => Err(BidError::InsufficientBalance {
+                        required,
+                        committed,
+                        available: signer_bal,
+                    }),
+                    false => Ok(()),
+                },
+            );
+        }
+        None => {
+            report.check("approval", Err::<(), _>(BidError::ApprovalNotFound));
+        }
     };
+    report.finish()?;
 
     println!("Valid!");
+    bid_contexts.add_pending(
+        signer_address,
+        settlement_contract,
+        auction_contract_address,
+        parsed_bid_values.auction_name.clone(),
+        bid_cost,
+    );
+    if let Some(nonce) = parsed_bid_values.nonce {
+        bid_contexts.add_pending_nonce(
+            signer_address,
+            auction_contract_address,
+            parsed_bid_values.auction_name.clone(),
+            nonce,
+        );
+    }
     Ok(Bid::new(
         bid_payload,
         parsed_bid_values,
+        typed_data_hash_bytes,
         received_time,
         auction,
     ))
 }
 
+fn check_auction_window(
+    auction: &Auction,
+    synced_block: Option<u64>,
+    (start_grace_blocks, end_margin_blocks): (u64, u64),
+) -> Result<(), BidError> {
+    let cur_synced_block = match synced_block {
+        Some(block) => block,
+        None => return Err(BidError::SyncedBlockNotFound),
+    };
+    if cur_synced_block < auction.start_block {
+        return Err(BidError::AuctionNotStarted {
+            auction_address: auction.address,
+            start_block: auction.start_block,
+            synced_block: cur_synced_block,
+        });
+    }
+    if cur_synced_block > auction.end_block {
+        return Err(BidError::AuctionEnded {
+            auction_address: auction.address,
+            end_block: auction.end_block,
+            synced_block: cur_synced_block,
+        });
+    }
+    // Bids near either end of the auction risk being reorged out or missing
+    // the settlement bundle
+    let open_block = auction.start_block.saturating_add(start_grace_blocks);
+    if cur_synced_block < open_block {
+        return Err(BidError::BiddingNotOpen {
+            auction_address: auction.address,
+            open_block,
+            synced_block: cur_synced_block,
+        });
+    }
+    let close_block = auction.end_block.saturating_sub(end_margin_blocks);
+    if cur_synced_block > close_block {
+        return Err(BidError::BiddingClosed {
+            auction_address: auction.address,
+            close_block,
+            end_block: auction.end_block,
+            synced_block: cur_synced_block,
+        });
+    }
+    Ok(())
+}
+
+fn check_deadline(
+    deadline: Option<Deadline>,
+    synced_block: Option<u64>,
+    received_time: DateTime<Utc>,
+) -> Result<(), BidError> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return Ok(()),
+    };
+    let (last, current) = match deadline {
+        Deadline::Block(block) => match synced_block {
+            Some(synced_block) => (block, synced_block),
+            None => return Err(BidError::SyncedBlockNotFound),
+        },
+        Deadline::Timestamp(timestamp) => (timestamp, received_time.timestamp() as u64),
+    };
+    match current > last {
+        true => Err(BidError::BidExpired { deadline, current }),
+        false => Ok(()),
+    }
+}
+
+fn invalid_typed_data(primary_type: &str, errors: &ValidationErrors) -> BidError {
+    let mut fields: Vec<String> = errors
+        .errors()
+        .keys()
+        .map(|field| field.to_string())
+        .collect();
+    fields.sort();
+    BidError::InvalidTypedData {
+        primary_type: primary_type.to_string(),
+        fields,
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct ResponseBody {
-    id: Option<String>,
-    cid: Option<String>,
-    error: Option<String>,
+    id: String,
+    cid: String,
 }
 
+const JSON_CONTENT_TYPE: &str = "application/json";
+const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
 fn build_response(status: StatusCode, message: &str) -> Result<Response<Body>, Error> {
-    match status {
-        StatusCode::OK => (),
-        _ => eprintln!("{}: {}", status, message),
-    };
-    let response_body: ResponseBody = match status {
-        StatusCode::OK => {
-            let h = Code::Sha2_256.digest(message.as_bytes());
-            let cid = Cid::new_v1(0x55, h);
-            ResponseBody {
-                id: Some(message.to_string()),
-                cid: Some(cid.to_string()),
-                error: None,
-            }
-        }
-        _ => ResponseBody {
-            id: None,
-            cid: None,
-            error: Some(message.to_string()),
-        },
+    let response_body = ResponseBody {
+        id: message.to_string(),
+        cid: compute_cid(message),
     };
-    let response_body_text = serde_json::to_string(&response_body).unwrap();
+    build_json_response(status, &response_body)
+}
+
+fn build_error_response(error: &BidError) -> Result<Response<Body>, Error> {
+    eprintln!("{}: {}", error.status(), error);
+    build_body_response(
+        error.status(),
+        PROBLEM_JSON_CONTENT_TYPE,
+        &ProblemDetails::from(error),
+    )
+}
+
+fn compute_cid(id: &str) -> String {
+    let h = Code::Sha2_256.digest(id.as_bytes());
+    Cid::new_v1(0x55, h).to_string()
+}
+
+fn build_json_response(status: StatusCode, body: &impl Serialize) -> Result<Response<Body>, Error> {
+    build_body_response(status, JSON_CONTENT_TYPE, body)
+}
+
+fn build_body_response(
+    status: StatusCode,
+    content_type: &str,
+    body: &impl Serialize,
+) -> Result<Response<Body>, Error> {
+    let response_body_text = serde_json::to_string(body)?;
     let res = match Response::builder()
+        .header("Content-Type", content_type)
         .header("Access-Control-Allow-Origin", "*")
-        .header("Access-Control-Allow-Methods", "PUT,OPTION")
+        .header(
+            "Access-Control-Allow-Methods",
+            "GET,POST,PUT,DELETE,OPTIONS",
+        )
         .header("Access-Control-Allow-Headers", "content-type")
         .status(status)
         .body(Body::from(response_body_text))
@@ -1,13 +1,382 @@
 use crate::bid::Bid;
+use crate::bid_payload::Deadline;
+use crate::error::BidError;
 use crate::utils::get_env_var;
 use crate::utils::Connectable;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use tokio_postgres::NoTls;
+use ethers::types::{Address, U256};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::{GenericClient, NoTls, Row, Transaction};
 
 #[async_trait]
 pub trait Database: Connectable {
-    async fn insert_bid(&mut self, bid: &Bid) -> Result<String, String>;
+    async fn insert_bid(&mut self, bid: &Bid) -> Result<StoredBid, BidError>;
+    async fn insert_bids(
+        &mut self,
+        bids: &[Bid],
+    ) -> Result<Vec<Result<StoredBid, BidError>>, BidError>;
+    async fn get_bid(&mut self, bid_id: &str) -> Result<Option<BidRecord>, BidError>;
+    async fn list_bids(&mut self, query: &BidQuery) -> Result<Vec<BidRecord>, BidError>;
+    async fn cancel_bid(
+        &mut self,
+        bid_id: &str,
+        auction_address: &Address,
+        auction_name: &str,
+        signer: &Address,
+    ) -> Result<Option<String>, BidError>;
+    async fn get_signer_exposure(
+        &mut self,
+        signer: &Address,
+        settlement_contract: &Address,
+        auction_address: &Address,
+        auction_name: &str,
+    ) -> Result<U256, BidError>;
+    async fn get_highest_nonce(
+        &mut self,
+        signer: &Address,
+        auction_address: &Address,
+        auction_name: &str,
+        excluded_bid_id: &str,
+    ) -> Result<Option<U256>, BidError>;
+}
+
+/// Row a Bid was stored in. Resubmitting a Bid leaves its existing row as it
+/// was, so `status` is that row's status, which may no longer be submitted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBid {
+    pub id: String,
+    pub inserted: bool,
+    pub status: String,
+}
+
+impl StoredBid {
+    pub fn is_active(&self) -> bool {
+        self.status == "submitted"
+    }
+}
+
+/// Filters and page position for listing a signer's bids, newest first
+#[derive(Debug, Clone, PartialEq)]
+pub struct BidQuery {
+    pub signer: Address,
+    pub auction_address: Option<Address>,
+    pub status: Option<String>,
+    pub cursor: Option<BidCursor>,
+    pub limit: u32,
+}
+
+/// Position of the last bid on a page. Bids sharing a `submitted_timestamp`
+/// are ordered by id so no bid is skipped between pages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BidCursor {
+    pub submitted_timestamp: DateTime<Utc>,
+    pub bid_id: String,
+}
+
+impl BidCursor {
+    pub fn from_record(bid_record: &BidRecord) -> BidCursor {
+        BidCursor {
+            submitted_timestamp: bid_record.submitted_timestamp,
+            bid_id: bid_record.id.clone(),
+        }
+    }
+}
+
+impl fmt::Display for BidCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}_{}",
+            self.submitted_timestamp.timestamp_micros(),
+            self.bid_id
+        )
+    }
+}
+
+impl FromStr for BidCursor {
+    type Err = String;
+
+    fn from_str(cursor: &str) -> Result<BidCursor, String> {
+        let (timestamp, bid_id) = cursor.split_once('_').ok_or("Invalid cursor")?;
+        let timestamp = timestamp.parse::<i64>().map_err(|_| "Invalid cursor")?;
+        let submitted_timestamp = DateTime::<Utc>::from_utc(
+            chrono::NaiveDateTime::from_timestamp_opt(
+                timestamp.div_euclid(1_000_000),
+                (timestamp.rem_euclid(1_000_000) * 1000) as u32,
+            )
+            .ok_or("Invalid cursor")?,
+            Utc,
+        );
+        let bid_id = bid_id.strip_prefix("0x").ok_or("Invalid cursor")?;
+        if bid_id.len() != 64 || hex::decode(bid_id).is_err() {
+            return Err("Invalid cursor".to_string());
+        }
+        Ok(BidCursor {
+            submitted_timestamp,
+            bid_id: "0x".to_string() + &bid_id.to_lowercase(),
+        })
+    }
+}
+
+/// A row of the `bids` table, as returned to bidders looking up their bid
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BidRecord {
+    pub id: String,
+    pub auction_address: String,
+    pub auction_name: String,
+    pub signer: String,
+    pub amount: String,
+    pub tip_hidden: String,
+    pub tip_revealed: Option<String>,
+    pub status: String,
+    pub replaced_by: Option<String>,
+    pub bundle_hash: Option<String>,
+    pub tx_hash: Option<String>,
+    pub submitted_timestamp: DateTime<Utc>,
+    pub status_last_updated: DateTime<Utc>,
+}
+
+// Columns selected when reading bids, in the order expected by `BidRecord::from_row`.
+// Numeric columns are cast to text since they may not fit in a native integer.
+const BID_RECORD_COLUMNS: &str = "
+    bid_id, auction_address, auction_name, signer, amount::TEXT, tip_hidden::TEXT,
+    tip_revealed::TEXT, status, replaced_by, bundle_hash, tx_hash, submitted_timestamp,
+    status_last_updated
+";
+
+impl BidRecord {
+    fn from_row(row: &Row) -> Result<BidRecord, BidError> {
+        let prefixed = |value: String| "0x".to_string() + &value;
+        let get_err = |e: tokio_postgres::Error| BidError::Database(e.to_string());
+        Ok(BidRecord {
+            id: prefixed(row.try_get(0).map_err(get_err)?),
+            auction_address: prefixed(row.try_get(1).map_err(get_err)?),
+            auction_name: row.try_get(2).map_err(get_err)?,
+            signer: prefixed(row.try_get(3).map_err(get_err)?),
+            amount: row.try_get(4).map_err(get_err)?,
+            tip_hidden: row.try_get(5).map_err(get_err)?,
+            tip_revealed: row.try_get(6).map_err(get_err)?,
+            status: row.try_get(7).map_err(get_err)?,
+            replaced_by: row
+                .try_get::<_, Option<String>>(8)
+                .map_err(get_err)?
+                .map(prefixed),
+            bundle_hash: row.try_get(9).map_err(get_err)?,
+            tx_hash: row.try_get(10).map_err(get_err)?,
+            submitted_timestamp: row.try_get(11).map_err(get_err)?,
+            status_last_updated: row.try_get(12).map_err(get_err)?,
+        })
+    }
+}
+
+/// Inserts a Bid, replacing the signer's earlier submitted Bid in the same
+/// auction. Inserting a Bid that already exists is a no-op.
+async fn insert_bid_in_transaction(
+    transaction: &Transaction<'_>,
+    bid: &Bid,
+) -> Result<StoredBid, BidError> {
+    let now: DateTime<Utc> = Utc::now();
+    let id = bid.hash();
+    let auction_address = hex::encode(bid.auction.address);
+    // Lowercase hex without 0x like auction_address, however the sender was
+    // written, so every query can compare signers as they are stored
+    let signer = match Address::from_str(&bid.payload.sender) {
+        Ok(signer) => hex::encode(signer),
+        Err(_) => return Err(BidError::InvalidSignerAddress),
+    };
+    // U256 has no postgres representation, so numerics are sent as text and cast
+    let amount = bid.parsed_values.amount.to_string();
+    let tip_hidden = bid.parsed_values.tip.to_string();
+    let base_price = bid.parsed_values.base_price.to_string();
+    let settlement_contract = hex::encode(bid.auction.settlement_contract);
+    let signature = bid
+        .payload
+        .signature
+        .strip_prefix("0x")
+        .unwrap_or(&bid.payload.signature);
+    // So the settlement side can drop expired Bids without parsing the payload
+    let (deadline_block, deadline_timestamp): (Option<i64>, Option<i64>) =
+        match bid.parsed_values.deadline {
+            Some(Deadline::Block(block)) => (Some(block as i64), None),
+            Some(Deadline::Timestamp(timestamp)) => (None, Some(timestamp as i64)),
+            None => (None, None),
+        };
+    let nonce = bid.parsed_values.nonce.map(|nonce| nonce.to_string());
+
+    // Serialises the Bids of a signer in an auction, so two Bids validated
+    // against the same highest nonce can't both be stored
+    let lock_key = format!(
+        "{}:{}:{}",
+        auction_address, bid.parsed_values.auction_name, signer
+    );
+    if let Err(e) = transaction
+        .execute("SELECT pg_advisory_xact_lock(hashtext($1))", &[&lock_key])
+        .await
+    {
+        return Err(BidError::Database(e.to_string()));
+    }
+
+    // Bid ids are derived from the signed payload, so this is a resubmission
+    // of a bid we already have
+    let existing = match transaction
+        .query_opt("SELECT status FROM bids WHERE bid_id = $1", &[&id])
+        .await
+    {
+        Ok(row) => row,
+        Err(e) => return Err(BidError::Database(e.to_string())),
+    };
+    if let Some(row) = existing {
+        println!("Bid already exists");
+        let status = match row.try_get::<_, String>(0) {
+            Ok(status) => status,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        return Ok(StoredBid {
+            id: "0x".to_string() + &id,
+            inserted: false,
+            status,
+        });
+    }
+
+    // Validation checked the nonce before the lock was taken. As there, a Bid
+    // without a nonce counts as nonce 0
+    let highest = query_highest_nonce(
+        transaction,
+        &signer,
+        &auction_address,
+        &bid.parsed_values.auction_name,
+        &id,
+    )
+    .await?;
+    let bid_nonce = bid.parsed_values.nonce.unwrap_or_default();
+    if let Some(highest) = highest.filter(|highest| bid_nonce <= *highest) {
+        return Err(BidError::StaleNonce {
+            nonce: bid_nonce,
+            highest,
+        });
+    }
+
+    let insert_statement = match transaction
+        .prepare(
+            "
+            INSERT INTO bids
+                (auction_address, auction_name, bundle_hash, tx_hash, bid_id, signer, amount, tip_hidden, tip_revealed, status, submitted_timestamp, status_last_updated, signature, settlement_contract, base_price, deadline_block, deadline_timestamp, nonce)
+            VALUES ($1, $2, NULL, NULL, $3, $4, $5::TEXT::NUMERIC, $6::TEXT::NUMERIC, NULL, 'submitted', $7, $8, $9, $10, $11::TEXT::NUMERIC, $12, $13, $14::TEXT::NUMERIC)
+            ",
+        )
+        .await
+    {
+        Ok(statement) => statement,
+        Err(e) => return Err(BidError::Database(e.to_string())),
+    };
+    if let Err(e) = transaction
+        .execute(
+            &insert_statement,
+            &[
+                &auction_address,
+                &bid.parsed_values.auction_name,
+                &id,
+                &signer,
+                &amount,
+                &tip_hidden,
+                &bid.received_time,
+                &now,
+                &signature,
+                &settlement_contract,
+                &base_price,
+                &deadline_block,
+                &deadline_timestamp,
+                &nonce,
+            ],
+        )
+        .await
+    {
+        return Err(BidError::Database(e.to_string()));
+    };
+
+    let replace_statement = match transaction
+        .prepare(
+            "
+            UPDATE bids SET
+                status = 'replaced',
+                replaced_by = $1
+            WHERE
+                auction_address = $2
+                AND auction_name = $3
+                AND signer = $4
+                AND status = 'submitted'
+                AND bid_id != $1
+                AND (nonce IS NULL OR nonce < $5::TEXT::NUMERIC)
+            ",
+        )
+        .await
+    {
+        Ok(statement) => statement,
+        Err(e) => return Err(BidError::Database(e.to_string())),
+    };
+    if let Err(e) = transaction
+        .execute(
+            &replace_statement,
+            &[
+                &id,
+                &auction_address,
+                &bid.parsed_values.auction_name,
+                &signer,
+                &nonce,
+            ],
+        )
+        .await
+    {
+        return Err(BidError::Database(e.to_string()));
+    };
+
+    Ok(StoredBid {
+        id: "0x".to_string() + &id,
+        inserted: true,
+        status: "submitted".to_string(),
+    })
+}
+
+/// Highest nonce of the signer's Bids in the auction other than
+/// `excluded_bid_id`. Addresses and the bid id are lowercase hex without 0x.
+async fn query_highest_nonce<C: GenericClient>(
+    client: &C,
+    signer: &str,
+    auction_address: &str,
+    auction_name: &str,
+    excluded_bid_id: &str,
+) -> Result<Option<U256>, BidError> {
+    let statement = "
+        SELECT MAX(nonce)::TEXT
+        FROM bids
+        WHERE
+            signer = $1
+            AND auction_address = $2
+            AND auction_name = $3
+            AND bid_id <> $4
+    ";
+    let row = match client
+        .query_one(
+            statement,
+            &[&signer, &auction_address, &auction_name, &excluded_bid_id],
+        )
+        .await
+    {
+        Ok(row) => row,
+        Err(e) => return Err(BidError::Database(e.to_string())),
+    };
+    let nonce = match row.try_get::<_, Option<String>>(0) {
+        Ok(nonce) => nonce,
+        Err(e) => return Err(BidError::Database(e.to_string())),
+    };
+    match nonce.map(|nonce| U256::from_dec_str(&nonce)).transpose() {
+        Ok(nonce) => Ok(nonce),
+        Err(e) => Err(BidError::Database(e.to_string())),
+    }
 }
 
 pub struct RdsProvider {
@@ -16,48 +385,309 @@ pub struct RdsProvider {
 
 #[async_trait]
 impl Database for RdsProvider {
-    async fn insert_bid(&mut self, bid: &Bid) -> Result<String, String> {
+    async fn insert_bid(&mut self, bid: &Bid) -> Result<StoredBid, BidError> {
         let client = match self.client.as_mut() {
             Some(client) => client,
-            None => return Err("Failed to get postgres client".to_string()),
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
         };
+        let transaction = match client.transaction().await {
+            Ok(transaction) => transaction,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        let stored_bid = insert_bid_in_transaction(&transaction, bid).await?;
 
-        let now: DateTime<Utc> = Utc::now();
-        let now_iso: String = now.to_rfc3339();
-        let id = bid.hash();
-        let query = format!(
+        // Dropping the transaction without committing rolls it back
+        match transaction.commit().await {
+            Ok(_) => Ok(stored_bid),
+            Err(e) => Err(BidError::Database(e.to_string())),
+        }
+    }
+
+    /// Inserts the Bids in one transaction. A Bid that fails doesn't stop the
+    /// others being inserted, so there is a result per Bid, in order.
+    async fn insert_bids(
+        &mut self,
+        bids: &[Bid],
+    ) -> Result<Vec<Result<StoredBid, BidError>>, BidError> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
+        };
+        let mut transaction = match client.transaction().await {
+            Ok(transaction) => transaction,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        let mut stored_bids = Vec::with_capacity(bids.len());
+        for bid in bids {
+            // Rolling back to the savepoint undoes only this Bid, e.g. one
+            // refused for its nonce, and leaves the rest of the batch
+            let savepoint = match transaction.savepoint("bid").await {
+                Ok(savepoint) => savepoint,
+                Err(e) => return Err(BidError::Database(e.to_string())),
+            };
+            let stored_bid = insert_bid_in_transaction(&savepoint, bid).await;
+            let released = match stored_bid {
+                Ok(_) => savepoint.commit().await,
+                Err(_) => savepoint.rollback().await,
+            };
+            if let Err(e) = released {
+                return Err(BidError::Database(e.to_string()));
+            }
+            stored_bids.push(stored_bid);
+        }
+        // A later Bid in the batch replaces an earlier one by the same signer in
+        // the same auction, so statuses are read again once all are inserted
+        let bid_ids: Vec<&str> = stored_bids
+            .iter()
+            .flatten()
+            .map(|stored_bid| &stored_bid.id[2..])
+            .collect();
+        let rows = match transaction
+            .query(
+                "SELECT bid_id, status FROM bids WHERE bid_id = ANY($1)",
+                &[&bid_ids],
+            )
+            .await
+        {
+            Ok(rows) => rows,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        let mut statuses = HashMap::with_capacity(rows.len());
+        for row in rows {
+            match (row.try_get::<_, String>(0), row.try_get::<_, String>(1)) {
+                (Ok(bid_id), Ok(status)) => statuses.insert(bid_id, status),
+                (Err(e), _) | (_, Err(e)) => return Err(BidError::Database(e.to_string())),
+            };
+        }
+        for stored_bid in stored_bids.iter_mut().flatten() {
+            if let Some(status) = statuses.get(&stored_bid.id[2..]) {
+                stored_bid.status = status.clone();
+            }
+        }
+
+        // Either every bid that didn't fail is inserted or none are
+        match transaction.commit().await {
+            Ok(_) => Ok(stored_bids),
+            Err(e) => Err(BidError::Database(e.to_string())),
+        }
+    }
+
+    async fn get_bid(&mut self, bid_id: &str) -> Result<Option<BidRecord>, BidError> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
+        };
+
+        let query = format!("SELECT {} FROM bids WHERE bid_id = $1", BID_RECORD_COLUMNS);
+        let bid_id = bid_id.strip_prefix("0x").unwrap_or(bid_id).to_lowercase();
+        match client.query_opt(&query, &[&bid_id]).await {
+            Ok(Some(row)) => Ok(Some(BidRecord::from_row(&row)?)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(BidError::Database(e.to_string())),
+        }
+    }
+
+    async fn list_bids(&mut self, query: &BidQuery) -> Result<Vec<BidRecord>, BidError> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
+        };
+
+        let statement = format!(
             "
-                BEGIN;
-                    INSERT INTO bids
-                        (auction_address, auction_name, bundle_hash, tx_hash, bid_id, signer, amount, tip_hidden, tip_revealed, status, submitted_timestamp, status_last_updated, signature)
-                    VALUES('{auction_address}', '{auction_name}', NULL, NULL, '{bid_id}', '{signer}', {amount}, {tip_hidden}, NULL, 'submitted', '{submitted_timestamp_iso}', '{now_iso}', '{signature}');
-
-                    UPDATE bids SET 
-                        status = 'replaced',
-                        replaced_by = '{bid_id}'
-                    WHERE
-                        auction_address = '{auction_address}'
-                        AND auction_name = '{auction_name}'
-                        AND signer = '{signer}'
-                        AND status = 'submitted'
-                        AND bid_id != '{bid_id}';
-                COMMIT;
-            ", 
-                bid_id=id,
-                now_iso=now_iso,
-                auction_name=bid.parsed_values.auction_name,
-                auction_address=hex::encode(bid.auction.address),
-                signer=&bid.payload.sender[2..],
-                amount=bid.parsed_values.amount,
-                tip_hidden=bid.parsed_values.tip,
-                submitted_timestamp_iso=bid.received_time.to_rfc3339(),
-                signature=&bid.payload.signature[2..],
+                SELECT {} FROM bids
+                WHERE
+                    signer = $1
+                    AND ($2::TEXT IS NULL OR auction_address = $2)
+                    AND ($3::TEXT IS NULL OR status = $3)
+                    AND ($4::TIMESTAMPTZ IS NULL OR (submitted_timestamp, bid_id) < ($4, $5))
+                ORDER BY submitted_timestamp DESC, bid_id DESC
+                LIMIT $6
+            ",
+            BID_RECORD_COLUMNS
         );
-        match client.batch_execute(&query).await {
-            Ok(_) => Ok("0x".to_string() + &id),
-            Err(e) => Err(e.to_string()),
+        let signer = hex::encode(query.signer);
+        let auction_address = query.auction_address.map(hex::encode);
+        let cursor_timestamp = query.cursor.as_ref().map(|c| c.submitted_timestamp);
+        let cursor_bid_id = query
+            .cursor
+            .as_ref()
+            .map(|c| c.bid_id[2..].to_string())
+            .unwrap_or_default();
+        let limit = query.limit as i64;
+        let rows = match client
+            .query(
+                &statement,
+                &[
+                    &signer,
+                    &auction_address,
+                    &query.status,
+                    &cursor_timestamp,
+                    &cursor_bid_id,
+                    &limit,
+                ],
+            )
+            .await
+        {
+            Ok(rows) => rows,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        rows.iter().map(BidRecord::from_row).collect()
+    }
+
+    async fn cancel_bid(
+        &mut self,
+        bid_id: &str,
+        auction_address: &Address,
+        auction_name: &str,
+        signer: &Address,
+    ) -> Result<Option<String>, BidError> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
+        };
+
+        let statement = "
+            UPDATE bids SET
+                status = 'cancelled',
+                status_last_updated = $5
+            WHERE
+                bid_id = $1
+                AND auction_address = $2
+                AND auction_name = $3
+                AND signer = $4
+                AND status = 'submitted'
+            RETURNING bid_id
+        ";
+        let now: DateTime<Utc> = Utc::now();
+        let bid_id = bid_id.strip_prefix("0x").unwrap_or(bid_id).to_lowercase();
+        match client
+            .query(
+                statement,
+                &[
+                    &bid_id,
+                    &hex::encode(auction_address),
+                    &auction_name,
+                    &hex::encode(signer),
+                    &now,
+                ],
+            )
+            .await
+        {
+            Ok(rows) => match rows.first() {
+                Some(row) => match row.try_get::<_, String>(0) {
+                    Ok(bid_id) => Ok(Some("0x".to_string() + &bid_id)),
+                    Err(e) => Err(BidError::Database(e.to_string())),
+                },
+                None => Ok(None),
+            },
+            Err(e) => Err(BidError::Database(e.to_string())),
         }
     }
+
+    /// Total cost of the signer's submitted bids on the settlement contract,
+    /// other than their bid in the given auction which a new bid replaces
+    async fn get_signer_exposure(
+        &mut self,
+        signer: &Address,
+        settlement_contract: &Address,
+        auction_address: &Address,
+        auction_name: &str,
+    ) -> Result<U256, BidError> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
+        };
+
+        let statement = "
+            SELECT COALESCE(SUM(amount * (base_price + tip_hidden)), 0)::TEXT
+            FROM bids
+            WHERE
+                signer = $1
+                AND settlement_contract = $2
+                AND status = 'submitted'
+                AND NOT (auction_address = $3 AND auction_name = $4)
+        ";
+        let row = match client
+            .query_one(
+                statement,
+                &[
+                    &hex::encode(signer),
+                    &hex::encode(settlement_contract),
+                    &hex::encode(auction_address),
+                    &auction_name,
+                ],
+            )
+            .await
+        {
+            Ok(row) => row,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        let exposure = match row.try_get::<_, String>(0) {
+            Ok(exposure) => exposure,
+            Err(e) => return Err(BidError::Database(e.to_string())),
+        };
+        match U256::from_dec_str(&exposure) {
+            Ok(exposure) => Ok(exposure),
+            Err(e) => Err(BidError::Database(e.to_string())),
+        }
+    }
+
+    /// Highest nonce of the signer's bids in the auction, whatever their
+    /// status, other than the given bid so it can be resubmitted
+    async fn get_highest_nonce(
+        &mut self,
+        signer: &Address,
+        auction_address: &Address,
+        auction_name: &str,
+        excluded_bid_id: &str,
+    ) -> Result<Option<U256>, BidError> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
+        };
+
+        let excluded_bid_id = excluded_bid_id
+            .strip_prefix("0x")
+            .unwrap_or(excluded_bid_id)
+            .to_lowercase();
+        query_highest_nonce(
+            &*client,
+            &hex::encode(signer),
+            &hex::encode(auction_address),
+            auction_name,
+            &excluded_bid_id,
+        )
+        .await
+    }
 }
 
 #[async_trait]
@@ -65,22 +695,26 @@ impl Connectable for RdsProvider {
     async fn is_connected(&self) -> bool {
         self.client.is_some()
     }
-    async fn ping(&mut self) -> Result<(), String> {
+    async fn ping(&mut self) -> Result<(), BidError> {
         let client = match self.client.as_mut() {
             Some(client) => client,
-            None => return Err("Failed to get postgres client".to_string()),
+            None => {
+                return Err(BidError::Database(
+                    "Failed to get postgres client".to_string(),
+                ))
+            }
         };
         match client.query("SELECT 1", &[]).await {
             Ok(_) => Ok(()),
-            Err(e) => Err(e.to_string()),
+            Err(e) => Err(BidError::Database(e.to_string())),
         }
     }
-    async fn connect(&mut self) -> Result<(), String> {
-        let host = get_env_var("RDS_HOST")?;
-        let port = get_env_var("RDS_PORT")?;
-        let user = get_env_var("RDS_USER")?;
-        let password = get_env_var("RDS_PASSWORD")?;
-        let dbname = get_env_var("RDS_DBNAME")?;
+    async fn connect(&mut self) -> Result<(), BidError> {
+        let host = get_env_var("RDS_HOST").map_err(BidError::Database)?;
+        let port = get_env_var("RDS_PORT").map_err(BidError::Database)?;
+        let user = get_env_var("RDS_USER").map_err(BidError::Database)?;
+        let password = get_env_var("RDS_PASSWORD").map_err(BidError::Database)?;
+        let dbname = get_env_var("RDS_DBNAME").map_err(BidError::Database)?;
         let connect_string = format!(
             "host={} port={} user={} password={} dbname={}",
             host, port, user, password, dbname
@@ -88,7 +722,7 @@ impl Connectable for RdsProvider {
 
         let (client, connection) = match tokio_postgres::connect(&connect_string, NoTls).await {
             Ok(client) => client,
-            Err(e) => return Err(e.to_string()),
+            Err(e) => return Err(BidError::Database(e.to_string())),
         };
 
         // The connection object performs the actual communication with the database,
@@ -1,8 +1,17 @@
-use crate::{auction::Auction, bid_payload::BidPayload};
-use eip_712::EIP712;
+use crate::{
+    auction::Auction,
+    bid::Bid,
+    bid_payload::BidPayload,
+    cancel_payload::CancelPayload,
+    chain_registry::{ChainConfig, ChainRegistry},
+    database::{BidRecord, StoredBid},
+};
+use chrono::{DateTime, Utc};
+use eip_712::{hash_structured_data, EIP712};
 use ethers::types::Address;
 use ethers::utils::parse_ether;
 use serde_json::from_str;
+use std::collections::HashMap;
 use std::str::FromStr;
 
 pub enum BidPayloadOption {
@@ -12,6 +21,21 @@ pub enum BidPayloadOption {
     InvalidSignature,
     InvalidBid,
     SignatureDoesNotMatchSigner,
+    BidderDoesNotMatchSigner,
+    // Valid Bid in another auction on the same auction contract
+    OtherAuction,
+    // Version 2 Bids with a deadline at block 150
+    DeadlineBlock,
+    // and at 2020-09-13 12:26:40 UTC
+    DeadlineTimestamp,
+    // Version 1 Bid with nonce 3
+    Nonce,
+}
+
+pub enum CancelPayloadOption {
+    Valid,
+    BidderDoesNotMatchSigner,
+    SignatureDoesNotMatchSigner,
 }
 
 pub enum AuctionOption {
@@ -20,6 +44,73 @@ pub enum AuctionOption {
     InvalidBasePrice,
 }
 
+pub const BID_ID: &str = "0x4f1a6f1c4a2dfc1eb1e7c4f2bd3a1e0c9e4c47d3ef0b86e0dd2ea2d3c0b1a9f7";
+pub const REPLACEMENT_BID_ID: &str =
+    "0x9c0e1f3a7b9d2c4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b5c7d9e1f2a4b6c8d0e";
+
+/// Newly inserted row for the Bid with the given id
+pub fn new_stored_bid(id: &str) -> StoredBid {
+    StoredBid {
+        id: id.to_string(),
+        inserted: true,
+        status: "submitted".to_string(),
+    }
+}
+
+pub fn new_bid_record(status: &str) -> BidRecord {
+    let replaced_by = match status {
+        "replaced" => Some(REPLACEMENT_BID_ID.to_string()),
+        _ => None,
+    };
+    BidRecord {
+        id: BID_ID.to_string(),
+        auction_address: "0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea".to_string(),
+        auction_name: "LeafyGreens_Public_Sale".to_string(),
+        signer: "0x36bcaee2f1f6c185f91608c7802f6fc4e8bd9f1d".to_string(),
+        amount: "5".to_string(),
+        tip_hidden: "100000000000000000".to_string(),
+        tip_revealed: None,
+        status: status.to_string(),
+        replaced_by,
+        bundle_hash: None,
+        tx_hash: None,
+        submitted_timestamp: DateTime::parse_from_rfc3339("2023-01-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc),
+        status_last_updated: DateTime::parse_from_rfc3339("2023-01-10T12:05:00Z")
+            .unwrap()
+            .with_timezone(&Utc),
+    }
+}
+
+/// Mainnet and Goerli, each with a single settlement contract and both Bid
+/// versions
+pub fn new_chain_registry() -> ChainRegistry {
+    let chain = |settlement_contract: &str| ChainConfig {
+        settlement_contracts: vec![Address::from_str(settlement_contract).unwrap()],
+        domain_name: "Pikapool Auction".to_string(),
+        domain_versions: vec!["1".to_string(), "2".to_string()],
+        max_sync_age: 60,
+        start_grace_blocks: 0,
+        end_margin_blocks: 0,
+    };
+    ChainRegistry::new(HashMap::from([
+        (
+            "1".to_string(),
+            chain("0xd2090025857B9C7B24387741f120538E928A3a59"),
+        ),
+        (
+            "5".to_string(),
+            chain("0xf2F1cb33141c931D2e81cD0572c97e5b2c63fD9c"),
+        ),
+    ]))
+}
+
+/// Unix time of a sync chain-state-service has just finished
+pub fn new_synced_timestamp() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
 pub fn new_auction(option: AuctionOption) -> Auction {
     match option {
         AuctionOption::InvalidBasePrice => Auction::new(
@@ -54,6 +145,41 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
         BidPayloadOption::InvalidAuctionAddress => "0x89q234r89hnbfgd",
         _ => "0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA",
     };
+    let bidder = match option {
+        BidPayloadOption::BidderDoesNotMatchSigner => "0x36bCaEE2F1f6C185f91608C7802f6Fc4E8bD9f1d",
+        BidPayloadOption::SignatureDoesNotMatchSigner => {
+            "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+        }
+        _ => "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
+    };
+    let auction_name = match option {
+        BidPayloadOption::OtherAuction => "LeafyGreens_Private_Sale",
+        _ => "LeafyGreens_Public_Sale",
+    };
+    // Extra fields at the end of the message and Bid type
+    let (version, extra_fields): (&str, &[(&str, &str)]) = match option {
+        BidPayloadOption::DeadlineBlock => (
+            "2",
+            &[("deadlineBlock", "0x96"), ("deadlineTimestamp", "0x0")],
+        ),
+        BidPayloadOption::DeadlineTimestamp => (
+            "2",
+            &[
+                ("deadlineBlock", "0x0"),
+                ("deadlineTimestamp", "0x5f5e1000"),
+            ],
+        ),
+        BidPayloadOption::Nonce => ("1", &[("nonce", "0x3")]),
+        _ => ("1", &[]),
+    };
+    let extra_value: String = extra_fields
+        .iter()
+        .map(|(field, value)| format!(r#", "{}": "{}""#, field, value))
+        .collect();
+    let extra_type: String = extra_fields
+        .iter()
+        .map(|(field, _)| format!(r#", {{ "name": "{}", "type": "uint256" }}"#, field))
+        .collect();
 
     let json = match option {
         // Typo in domain.name
@@ -69,6 +195,7 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
             "message": {
                 "auctionAddress": "0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA",
                 "auctionName": "LeafyGreens_Public_Sale",
+                "bidder": "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
                 "amount": "0x5",
                 "basePrice": "0x03782dace9d90000",
                 "tip": "0x016345785d8a0000"
@@ -101,6 +228,10 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
                         "name": "auctionAddress",
                         "type": "address"
                     },
+                    {
+                        "name": "bidder",
+                        "type": "address"
+                    },
                     {
                         "name": "amount",
                         "type": "uint256"
@@ -122,16 +253,17 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
         "primaryType": "Bid",
         "domain": {{
             "name": "Pikapool Auction",
-            "version": "1",
+            "version": "{}",
             "chainId": "0x1",
             "verifyingContract": "0xd2090025857B9C7B24387741f120538E928A3a59"
         }},
         "message": {{
-            "auctionName": "LeafyGreens_Public_Sale",
+            "auctionName": "{}",
             "auctionAddress": "{}",
+            "bidder": "{}",
             "amount": "0x5",
             "basePrice": "0x03782dace9d90000",
-            "tip": "0x016345785d8a0000"
+            "tip": "0x016345785d8a0000"{}
         }},
         "types": {{
             "EIP712Domain": [
@@ -161,6 +293,10 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
                     "name": "auctionAddress",
                     "type": "address"
                 }},
+                {{
+                    "name": "bidder",
+                    "type": "address"
+                }},
                 {{
                     "name": "amount",
                     "type": "uint256"
@@ -172,11 +308,11 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
                 {{
                     "name": "tip",
                     "type": "uint256"
-                }}
+                }}{}
             ]
         }}
 	}}"#,
-            auction_address
+            version, auction_name, auction_address, bidder, extra_value, extra_type
         ),
     };
     let typed_data = match from_str::<EIP712>(json.as_str()) {
@@ -189,11 +325,16 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
         BidPayloadOption::SignatureDoesNotMatchSigner => {
             "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
         }
-        _ => "0x36bCaEE2F1f6C185f91608C7802f6Fc4E8bD9f1d",
+        _ => "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
     };
     let signature = match option {
         BidPayloadOption::InvalidSignature => "0xakljsdfjhk",
-        _ => "0x1dd291a7d84c8e680c7132ca4812b0b54332bbc26a01afd812c790d8bacfdfd845b11da309d387c20be7a79cef88c38880db081f112a70a65c469a552685880d1b",
+        BidPayloadOption::DeadlineBlock => "0x21dbfc0848a357ebfa20eb567f5616946dac7d34f04cc8f1f4429f420eb9cb40433f676408f009588329db53d3811400150a392fa5ca969250a0c765c8cb57831b",
+        BidPayloadOption::Nonce => "0x8726d7afb8bff4913e2a150c7a64a9bd88cb5a1fc800a6d77626620ff70388123fc95cdc9200eaa3c22bf576a2cc4da04778bfedd708ac28cee4f130a1dc3ca61c",
+        BidPayloadOption::DeadlineTimestamp => "0x6064984150713f51670ac73b5c46d7a49cc3b7fe8c6463b5d1f6de5fcdf3198921f6b307746323ee42a333523b2f39e7f82c307a1414266015a32a65ec4245f61c",
+        BidPayloadOption::BidderDoesNotMatchSigner => "0x8b08683a9836a62dc4e65dd7fb76e22097a9a88500021ffa361f15c4e7d9f25f617c77296484d28908cc09e65a55f28af3a456c66090b4840e78508218bba5a11c",
+        BidPayloadOption::OtherAuction => "0xac8f1b3643cd68bc723053ec8c65fb502b8a47aac58a619d66176761ffb24dad4620848c765dfb3f0cf9ce65f188400299e3158938efb05a24dc9f3022db4b3c1b",
+        _ => "0x881d5c3742e33a662fb2de9f906ac084badb4faf630e79acea39a9f3951e042c5769af5838bf3e5fe80705a3ae01b900a6ef119267322c11b8ca06e5de57f4f01c",
     };
 
     BidPayload {
@@ -202,3 +343,115 @@ pub fn new_bid_payload(option: BidPayloadOption) -> BidPayload {
         signature: signature.to_string(),
     }
 }
+
+/// Bid from `new_bid_payload`, moved to the named auction
+pub fn new_bid(option: BidPayloadOption, auction_name: &str) -> Bid {
+    let mut bid_payload = new_bid_payload(option);
+    bid_payload.typed_data.message["auctionName"] = auction_name.into();
+    let typed_data_hash = match hash_structured_data(bid_payload.typed_data.clone()) {
+        Ok(hash) => hash.into(),
+        Err(e) => panic!("Error hashing typed data: {}", e),
+    };
+    let parsed_values = match bid_payload.parse_values() {
+        Ok(parsed_values) => parsed_values,
+        Err(e) => panic!("Error parsing bid payload: {}", e),
+    };
+    let mut auction = new_auction(AuctionOption::Valid);
+    auction.name = auction_name.to_string();
+    Bid::new(
+        bid_payload,
+        parsed_values,
+        typed_data_hash,
+        Utc::now(),
+        auction,
+    )
+}
+
+pub fn new_cancel_payload(option: CancelPayloadOption) -> CancelPayload {
+    let bidder = match option {
+        CancelPayloadOption::BidderDoesNotMatchSigner => {
+            "0x36bCaEE2F1f6C185f91608C7802f6Fc4E8bD9f1d"
+        }
+        CancelPayloadOption::SignatureDoesNotMatchSigner => {
+            "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+        }
+        _ => "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
+    };
+    let json = format!(
+        r#"{{
+        "primaryType": "Cancel",
+        "domain": {{
+            "name": "Pikapool Auction",
+            "version": "1",
+            "chainId": "0x1",
+            "verifyingContract": "0xd2090025857B9C7B24387741f120538E928A3a59"
+        }},
+        "message": {{
+            "auctionName": "LeafyGreens_Public_Sale",
+            "auctionAddress": "0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA",
+            "bidder": "{}",
+            "bidId": "{}"
+        }},
+        "types": {{
+            "EIP712Domain": [
+                {{
+                        "name": "name",
+                        "type": "string"
+                }},
+                {{
+                        "name": "version",
+                        "type": "string"
+                }},
+                {{
+                        "name": "chainId",
+                        "type": "uint256"
+                }},
+                {{
+                        "name": "verifyingContract",
+                        "type": "address"
+                }}
+            ],
+            "Cancel": [
+                {{
+                    "name": "auctionName",
+                    "type": "string"
+                }},
+                {{
+                    "name": "auctionAddress",
+                    "type": "address"
+                }},
+                {{
+                    "name": "bidder",
+                    "type": "address"
+                }},
+                {{
+                    "name": "bidId",
+                    "type": "bytes32"
+                }}
+            ]
+        }}
+	}}"#,
+        bidder, BID_ID
+    );
+    let typed_data = match from_str::<EIP712>(json.as_str()) {
+        Ok(typed_data) => typed_data,
+        Err(e) => panic!("Error parsing typed data: {}", e),
+    };
+
+    let sender = match option {
+        CancelPayloadOption::SignatureDoesNotMatchSigner => {
+            "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+        }
+        _ => "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23",
+    };
+    let signature = match option {
+        CancelPayloadOption::BidderDoesNotMatchSigner => "0xbe45b27241c88c6eed893b9fae9b8e58dab8f26c1827e528ac45b9ba0037450e7cd63c3d448501cea633a0a2a32bc1b679e18e55b531304be757eb8452e6d5741b",
+        _ => "0x3a0cbfca509887a38d6373bd0bb02da70d41cc109ceae7d4c195b0d82b1b1fb43b18f25534230f8cbbe4feef863bf69d13d273fd462c241a501db5f64a4ff87c1c",
+    };
+
+    CancelPayload {
+        typed_data,
+        sender: sender.to_string(),
+        signature: signature.to_string(),
+    }
+}
@@ -0,0 +1,618 @@
+use crate::bid_payload::Deadline;
+use ethers::types::{Address, U256};
+use lambda_http::http::StatusCode;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// Every way handling a request can fail. Each variant maps to a stable
+/// machine-readable code and an HTTP status.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BidError {
+    RequestBodyMissing,
+    MalformedPayload(String),
+    InvalidTypedData {
+        primary_type: String,
+        fields: Vec<String>,
+    },
+    UnsupportedChain {
+        chain_id: String,
+    },
+    UnsupportedSettlementContract {
+        chain_id: String,
+        settlement_contract: Address,
+    },
+    InvalidSignerAddress,
+    InvalidAuctionAddress,
+    InvalidSignature,
+    NonCanonicalSignature,
+    SignatureMismatch,
+    BidderMismatch,
+    AuctionNotFound {
+        auction_address: Address,
+    },
+    SettlementContractMismatch {
+        auction_address: Address,
+        expected: Address,
+        provided: Address,
+    },
+    BasePriceMismatch {
+        auction_address: Address,
+        expected: U256,
+        provided: U256,
+    },
+    SyncedBlockNotFound,
+    ChainStateStale {
+        synced_timestamp: Option<u64>,
+        received_timestamp: u64,
+        max_sync_age: u64,
+    },
+    AuctionNotStarted {
+        auction_address: Address,
+        start_block: u64,
+        synced_block: u64,
+    },
+    AuctionEnded {
+        auction_address: Address,
+        end_block: u64,
+        synced_block: u64,
+    },
+    BiddingNotOpen {
+        auction_address: Address,
+        open_block: u64,
+        synced_block: u64,
+    },
+    BiddingClosed {
+        auction_address: Address,
+        close_block: u64,
+        end_block: u64,
+        synced_block: u64,
+    },
+    /// `current` is the latest synced block for block deadlines and the
+    /// time the Bid was received for timestamp deadlines
+    BidExpired {
+        deadline: Deadline,
+        current: u64,
+    },
+    /// `highest` is the highest nonce of the signer's other Bids in the auction
+    StaleNonce {
+        nonce: U256,
+        highest: U256,
+    },
+    ApprovalNotFound,
+    /// `required` covers the Bid and the `committed` cost of the signer's
+    /// other submitted Bids on the same settlement contract
+    InsufficientApproval {
+        required: U256,
+        committed: U256,
+        available: U256,
+    },
+    InsufficientBalance {
+        required: U256,
+        committed: U256,
+        available: U256,
+    },
+    InvalidBidId,
+    BidNotFound,
+    NoActiveBid,
+    BidNotActive {
+        status: String,
+    },
+    InvalidQuery(String),
+    InvalidBatchSize {
+        size: usize,
+        max: usize,
+    },
+    MethodNotImplemented,
+    Config(String),
+    Cache(String),
+    /// A hash in the cache is missing some of its fields
+    CorruptCacheEntry {
+        key: String,
+        missing_fields: Vec<String>,
+    },
+    /// A cached value, or hash `field`, is not a valid number or address
+    MalformedCacheValue {
+        key: String,
+        field: Option<String>,
+        value: String,
+    },
+    Database(String),
+    Publisher(String),
+    ContractWalletVerifier(String),
+    ConnectionPool(String),
+    Multiple(Vec<BidError>),
+}
+
+impl BidError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            BidError::RequestBodyMissing => "REQUEST_BODY_MISSING",
+            BidError::MalformedPayload(_) => "MALFORMED_PAYLOAD",
+            BidError::InvalidTypedData { .. } => "INVALID_TYPED_DATA",
+            BidError::UnsupportedChain { .. } => "UNSUPPORTED_CHAIN",
+            BidError::UnsupportedSettlementContract { .. } => "UNSUPPORTED_SETTLEMENT_CONTRACT",
+            BidError::InvalidSignerAddress => "INVALID_SIGNER_ADDRESS",
+            BidError::InvalidAuctionAddress => "INVALID_AUCTION_ADDRESS",
+            BidError::InvalidSignature => "INVALID_SIGNATURE",
+            BidError::NonCanonicalSignature => "NON_CANONICAL_SIGNATURE",
+            BidError::SignatureMismatch => "SIGNATURE_MISMATCH",
+            BidError::BidderMismatch => "BIDDER_MISMATCH",
+            BidError::AuctionNotFound { .. } => "AUCTION_NOT_FOUND",
+            BidError::SettlementContractMismatch { .. } => "SETTLEMENT_CONTRACT_MISMATCH",
+            BidError::BasePriceMismatch { .. } => "BASE_PRICE_MISMATCH",
+            BidError::SyncedBlockNotFound => "SYNCED_BLOCK_NOT_FOUND",
+            BidError::ChainStateStale { .. } => "CHAIN_STATE_STALE",
+            BidError::AuctionNotStarted { .. } => "AUCTION_NOT_STARTED",
+            BidError::AuctionEnded { .. } => "AUCTION_ENDED",
+            BidError::BiddingNotOpen { .. } => "BIDDING_NOT_OPEN",
+            BidError::BiddingClosed { .. } => "BIDDING_CLOSED",
+            BidError::BidExpired { .. } => "BID_EXPIRED",
+            BidError::StaleNonce { .. } => "STALE_NONCE",
+            BidError::ApprovalNotFound => "APPROVAL_NOT_FOUND",
+            BidError::InsufficientApproval { .. } => "INSUFFICIENT_APPROVAL",
+            BidError::InsufficientBalance { .. } => "INSUFFICIENT_BALANCE",
+            BidError::InvalidBidId => "INVALID_BID_ID",
+            BidError::BidNotFound => "BID_NOT_FOUND",
+            BidError::NoActiveBid => "NO_ACTIVE_BID",
+            BidError::BidNotActive { .. } => "BID_NOT_ACTIVE",
+            BidError::InvalidQuery(_) => "INVALID_QUERY",
+            BidError::InvalidBatchSize { .. } => "INVALID_BATCH_SIZE",
+            BidError::MethodNotImplemented => "METHOD_NOT_IMPLEMENTED",
+            BidError::Config(_) => "CONFIG_ERROR",
+            BidError::Cache(_) => "CACHE_ERROR",
+            BidError::CorruptCacheEntry { .. } => "CORRUPT_CACHE_ENTRY",
+            BidError::MalformedCacheValue { .. } => "MALFORMED_CACHE_VALUE",
+            BidError::Database(_) => "DATABASE_ERROR",
+            BidError::Publisher(_) => "PUBLISHER_ERROR",
+            BidError::ContractWalletVerifier(_) => "CONTRACT_WALLET_VERIFIER_ERROR",
+            BidError::ConnectionPool(_) => "CONNECTION_POOL_ERROR",
+            BidError::Multiple(_) => "MULTIPLE_ERRORS",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            BidError::ApprovalNotFound
+            | BidError::InsufficientApproval { .. }
+            | BidError::InsufficientBalance { .. } => StatusCode::FORBIDDEN,
+            BidError::BidNotFound | BidError::NoActiveBid => StatusCode::NOT_FOUND,
+            BidError::BidNotActive { .. } => StatusCode::CONFLICT,
+            BidError::MethodNotImplemented => StatusCode::NOT_IMPLEMENTED,
+            BidError::ChainStateStale { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            BidError::SyncedBlockNotFound
+            | BidError::Config(_)
+            | BidError::Cache(_)
+            | BidError::CorruptCacheEntry { .. }
+            | BidError::MalformedCacheValue { .. }
+            | BidError::Database(_)
+            | BidError::Publisher(_)
+            | BidError::ContractWalletVerifier(_)
+            | BidError::ConnectionPool(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            // Shares the status of its errors when they agree
+            BidError::Multiple(errors) => {
+                let statuses: Vec<StatusCode> = errors.iter().map(|e| e.status()).collect();
+                if statuses.windows(2).all(|pair| pair[0] == pair[1]) && !statuses.is_empty() {
+                    statuses[0]
+                } else if statuses.iter().any(|status| status.is_server_error()) {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                } else {
+                    StatusCode::BAD_REQUEST
+                }
+            }
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Short summary that is the same for every occurrence of the error
+    pub fn title(&self) -> &'static str {
+        match self {
+            BidError::RequestBodyMissing => "Request body missing",
+            BidError::MalformedPayload(_) => "Malformed payload",
+            BidError::InvalidTypedData { .. } => "Invalid typed_data",
+            BidError::UnsupportedChain { .. } => "Chain is not supported",
+            BidError::UnsupportedSettlementContract { .. } => {
+                "Settlement contract is not supported on this chain"
+            }
+            BidError::InvalidSignerAddress => "Invalid signer address",
+            BidError::InvalidAuctionAddress => "Invalid auction contract address",
+            BidError::InvalidSignature => "Invalid signature",
+            BidError::NonCanonicalSignature => {
+                "Signature s value is not in the lower half of the curve order"
+            }
+            BidError::SignatureMismatch => "Signature does not match signer",
+            BidError::BidderMismatch => "Bidder does not match signer",
+            BidError::AuctionNotFound { .. } => "Specified auction does not exist",
+            BidError::SettlementContractMismatch { .. } => {
+                "Specified settlement contract does not match auction settlement contract"
+            }
+            BidError::BasePriceMismatch { .. } => {
+                "Specified base_price does not match auction base_price"
+            }
+            BidError::SyncedBlockNotFound => "Synced block not found",
+            BidError::ChainStateStale { .. } => "Chain state is out of date",
+            BidError::AuctionNotStarted { .. } => "Auction has not started",
+            BidError::AuctionEnded { .. } => "Auction has ended",
+            BidError::BiddingNotOpen { .. } => "Bidding has not opened",
+            BidError::BiddingClosed { .. } => "Bidding has closed",
+            BidError::BidExpired { .. } => "Bid has expired",
+            BidError::StaleNonce { .. } => "Nonce is not above the signer's highest nonce",
+            BidError::ApprovalNotFound => "Signer has not approved the settlement contract",
+            BidError::InsufficientApproval { .. } => "Signer approval amount is insufficient",
+            BidError::InsufficientBalance { .. } => "Signer token balance is insufficient",
+            BidError::InvalidBidId => "Invalid bid id",
+            BidError::BidNotFound => "Bid does not exist",
+            BidError::NoActiveBid => "Signer has no active bid with the specified id",
+            BidError::BidNotActive { .. } => "Bid is no longer active",
+            BidError::InvalidQuery(_) => "Invalid query",
+            BidError::InvalidBatchSize { .. } => "Invalid batch size",
+            BidError::MethodNotImplemented => "Method not implemented",
+            BidError::Config(_) => "Configuration error",
+            BidError::Cache(_) => "Cache error",
+            BidError::CorruptCacheEntry { .. } => "Cache entry is missing fields",
+            BidError::MalformedCacheValue { .. } => "Cache value is malformed",
+            BidError::Database(_) => "Database error",
+            BidError::Publisher(_) => "Publisher error",
+            BidError::ContractWalletVerifier(_) => "Contract wallet verifier error",
+            BidError::ConnectionPool(_) => "Connection pool error",
+            BidError::Multiple(_) => "Bid failed multiple checks",
+        }
+    }
+
+    /// Values behind the failure, e.g. the auction window or the required and
+    /// available amounts. Amounts are decimal strings since they can exceed
+    /// JSON number precision.
+    pub fn extensions(&self) -> Map<String, Value> {
+        let extensions = match self {
+            BidError::InvalidTypedData { fields, .. } if !fields.is_empty() => json!({
+                "fields": fields,
+            }),
+            BidError::UnsupportedChain { chain_id } => json!({
+                "chain_id": chain_id,
+            }),
+            BidError::UnsupportedSettlementContract {
+                chain_id,
+                settlement_contract,
+            } => json!({
+                "chain_id": chain_id,
+                "settlement_contract": format!("{:?}", settlement_contract),
+            }),
+            BidError::AuctionNotFound { auction_address } => json!({
+                "auction_address": format!("{:?}", auction_address),
+            }),
+            BidError::SettlementContractMismatch {
+                auction_address,
+                expected,
+                provided,
+            } => json!({
+                "auction_address": format!("{:?}", auction_address),
+                "expected": format!("{:?}", expected),
+                "provided": format!("{:?}", provided),
+            }),
+            BidError::BasePriceMismatch {
+                auction_address,
+                expected,
+                provided,
+            } => json!({
+                "auction_address": format!("{:?}", auction_address),
+                "expected": expected.to_string(),
+                "provided": provided.to_string(),
+            }),
+            BidError::ChainStateStale {
+                synced_timestamp,
+                received_timestamp,
+                max_sync_age,
+            } => json!({
+                "synced_timestamp": synced_timestamp,
+                "received_timestamp": received_timestamp,
+                "max_sync_age": max_sync_age,
+            }),
+            BidError::AuctionNotStarted {
+                auction_address,
+                start_block,
+                synced_block,
+            } => json!({
+                "auction_address": format!("{:?}", auction_address),
+                "start_block": start_block,
+                "synced_block": synced_block,
+            }),
+            BidError::AuctionEnded {
+                auction_address,
+                end_block,
+                synced_block,
+            } => json!({
+                "auction_address": format!("{:?}", auction_address),
+                "end_block": end_block,
+                "synced_block": synced_block,
+            }),
+            BidError::BiddingNotOpen {
+                auction_address,
+                open_block,
+                synced_block,
+            } => json!({
+                "auction_address": format!("{:?}", auction_address),
+                "open_block": open_block,
+                "synced_block": synced_block,
+            }),
+            BidError::BiddingClosed {
+                auction_address,
+                close_block,
+                end_block,
+                synced_block,
+            } => json!({
+                "auction_address": format!("{:?}", auction_address),
+                "close_block": close_block,
+                "end_block": end_block,
+                "synced_block": synced_block,
+            }),
+            BidError::BidExpired { deadline, current } => match deadline {
+                Deadline::Block(deadline) => json!({
+                    "deadline_block": deadline,
+                    "synced_block": current,
+                }),
+                Deadline::Timestamp(deadline) => json!({
+                    "deadline_timestamp": deadline,
+                    "received_timestamp": current,
+                }),
+            },
+            BidError::StaleNonce { nonce, highest } => json!({
+                "nonce": nonce.to_string(),
+                "highest_nonce": highest.to_string(),
+            }),
+            BidError::InsufficientApproval {
+                required,
+                committed,
+                available,
+            }
+            | BidError::InsufficientBalance {
+                required,
+                committed,
+                available,
+            } => json!({
+                "required": required.to_string(),
+                "committed": committed.to_string(),
+                "available": available.to_string(),
+            }),
+            BidError::BidNotActive { status } => json!({
+                "bid_status": status,
+            }),
+            BidError::InvalidBatchSize { size, max } => json!({
+                "size": size,
+                "max": max,
+            }),
+            BidError::CorruptCacheEntry {
+                key,
+                missing_fields,
+            } => json!({
+                "key": key,
+                "missing_fields": missing_fields,
+            }),
+            BidError::MalformedCacheValue { key, field, value } => match field {
+                Some(field) => json!({
+                    "key": key,
+                    "field": field,
+                    "value": value,
+                }),
+                None => json!({
+                    "key": key,
+                    "value": value,
+                }),
+            },
+            BidError::Multiple(errors) => json!({
+                "errors": errors.iter().map(ProblemDetails::from).collect::<Vec<_>>(),
+            }),
+            _ => json!({}),
+        };
+        match extensions {
+            Value::Object(extensions) => extensions,
+            _ => Map::new(),
+        }
+    }
+}
+
+/// Explanation specific to this occurrence of the error
+impl fmt::Display for BidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BidError::MalformedPayload(e)
+            | BidError::InvalidQuery(e)
+            | BidError::Config(e)
+            | BidError::Cache(e)
+            | BidError::Database(e)
+            | BidError::Publisher(e)
+            | BidError::ContractWalletVerifier(e)
+            | BidError::ConnectionPool(e) => write!(f, "{}", e),
+            BidError::CorruptCacheEntry {
+                key,
+                missing_fields,
+            } => write!(
+                f,
+                "Cache entry {} is missing {}",
+                key,
+                missing_fields.join(", ")
+            ),
+            BidError::MalformedCacheValue { key, field, value } => match field {
+                Some(field) => write!(f, "Cache entry {} has malformed {} {:?}", key, field, value),
+                None => write!(f, "Cache entry {} has malformed value {:?}", key, value),
+            },
+            BidError::InvalidTypedData {
+                primary_type,
+                fields,
+            } => match fields.is_empty() {
+                true => write!(f, "typed_data is not a valid Pikapool {}", primary_type),
+                false => write!(
+                    f,
+                    "typed_data is not a valid Pikapool {}: invalid {}",
+                    primary_type,
+                    fields.join(", ")
+                ),
+            },
+            BidError::Multiple(errors) => {
+                let details: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", details.join("; "))
+            }
+            BidError::UnsupportedChain { chain_id } => {
+                write!(f, "Chain {} is not supported", chain_id)
+            }
+            BidError::UnsupportedSettlementContract {
+                chain_id,
+                settlement_contract,
+            } => write!(
+                f,
+                "Settlement contract {:?} is not supported on chain {}",
+                settlement_contract, chain_id
+            ),
+            BidError::BasePriceMismatch {
+                expected, provided, ..
+            } => write!(
+                f,
+                "Specified base_price {} does not match auction base_price {}",
+                provided, expected
+            ),
+            BidError::ChainStateStale {
+                synced_timestamp,
+                received_timestamp,
+                max_sync_age,
+            } => match synced_timestamp {
+                Some(synced_timestamp) => write!(
+                    f,
+                    "Chain state was last synced at {} but must be synced within {} seconds of {}",
+                    synced_timestamp, max_sync_age, received_timestamp
+                ),
+                None => write!(f, "Chain state has no sync time"),
+            },
+            BidError::AuctionNotStarted {
+                start_block,
+                synced_block,
+                ..
+            } => write!(
+                f,
+                "Auction starts at block {} but the latest synced block is {}",
+                start_block, synced_block
+            ),
+            BidError::AuctionEnded {
+                end_block,
+                synced_block,
+                ..
+            } => write!(
+                f,
+                "Auction ended at block {} but the latest synced block is {}",
+                end_block, synced_block
+            ),
+            BidError::BiddingNotOpen {
+                open_block,
+                synced_block,
+                ..
+            } => write!(
+                f,
+                "Bidding opens at block {} but the latest synced block is {}",
+                open_block, synced_block
+            ),
+            BidError::BiddingClosed {
+                close_block,
+                end_block,
+                synced_block,
+                ..
+            } => write!(
+                f,
+                "Bidding closed at block {} ahead of the auction ending at block {} but the latest synced block is {}",
+                close_block, end_block, synced_block
+            ),
+            BidError::BidExpired { deadline, current } => match deadline {
+                Deadline::Block(deadline) => write!(
+                    f,
+                    "Bid expired at block {} but the latest synced block is {}",
+                    deadline, current
+                ),
+                Deadline::Timestamp(deadline) => write!(
+                    f,
+                    "Bid expired at timestamp {} but was received at {}",
+                    deadline, current
+                ),
+            },
+            BidError::StaleNonce { nonce, highest } => write!(
+                f,
+                "Nonce {} must be above the signer's highest nonce {} in this auction",
+                nonce, highest
+            ),
+            BidError::InsufficientApproval {
+                required,
+                committed,
+                available,
+            } => write!(
+                f,
+                "{} but the signer approved {}",
+                BidCost(required, committed),
+                available
+            ),
+            BidError::InsufficientBalance {
+                required,
+                committed,
+                available,
+            } => write!(
+                f,
+                "{} but the signer balance is {}",
+                BidCost(required, committed),
+                available
+            ),
+            BidError::BidNotActive { status } => write!(f, "Bid has already been {}", status),
+            BidError::InvalidBatchSize { size, max } => write!(
+                f,
+                "Batch has {} bids but must have between 1 and {}",
+                size, max
+            ),
+            _ => write!(f, "{}", self.title()),
+        }
+    }
+}
+
+impl std::error::Error for BidError {}
+
+/// What a Bid requires of the signer, given the total required and the part
+/// already committed to their other Bids
+struct BidCost<'a>(&'a U256, &'a U256);
+
+impl fmt::Display for BidCost<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let BidCost(required, committed) = self;
+        match committed.is_zero() {
+            true => write!(f, "Bid costs {}", required),
+            false => write!(
+                f,
+                "Bid costs {} on top of {} committed to the signer's other bids",
+                **required - **committed,
+                committed
+            ),
+        }
+    }
+}
+
+/// RFC 7807 problem details, with the bid-specific context of the error
+/// flattened in as extension members
+#[derive(Debug, Serialize)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    #[serde(flatten)]
+    pub extensions: Map<String, Value>,
+}
+
+impl From<&BidError> for ProblemDetails {
+    fn from(error: &BidError) -> Self {
+        ProblemDetails {
+            problem_type: format!(
+                "urn:pikapool:error:{}",
+                error.code().to_lowercase().replace('_', "-")
+            ),
+            title: error.title(),
+            status: error.status().as_u16(),
+            detail: error.to_string(),
+            code: error.code(),
+            extensions: error.extensions(),
+        }
+    }
+}
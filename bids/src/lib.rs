@@ -2,8 +2,14 @@ pub mod auction;
 pub mod bid;
 pub mod bid_payload;
 pub mod cache;
+pub mod cancel_payload;
+pub mod chain_registry;
+pub mod contract_wallet;
 pub mod core;
 pub mod database;
 pub mod dummy_data;
+pub mod error;
+pub mod publisher;
 pub mod signature_validation;
 pub mod utils;
+pub mod validation;
@@ -0,0 +1,99 @@
+use crate::bid::Bid;
+use crate::bid_payload::{BidPayload, ParsedValues};
+use crate::error::BidError;
+use crate::utils::get_env_var;
+use crate::utils::Connectable;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusoto_core::Region;
+use rusoto_sqs::{GetQueueAttributesRequest, SendMessageRequest, Sqs, SqsClient};
+use serde::Serialize;
+use std::env;
+
+#[async_trait]
+pub trait Publisher: Connectable {
+    async fn publish_bid(&mut self, bid: &Bid, id: &str, cid: &str) -> Result<(), BidError>;
+}
+
+/// Message published for every accepted Bid
+#[derive(Debug, Serialize)]
+pub struct BidMessage<'a> {
+    pub id: &'a str,
+    pub cid: &'a str,
+    pub payload: &'a BidPayload,
+    pub parsed_values: &'a ParsedValues,
+    pub received_time: &'a DateTime<Utc>,
+}
+
+pub struct SqsPublisher {
+    pub client: Option<SqsClient>,
+    pub queue_url: Option<String>,
+}
+
+#[async_trait]
+impl Publisher for SqsPublisher {
+    async fn publish_bid(&mut self, bid: &Bid, id: &str, cid: &str) -> Result<(), BidError> {
+        let (client, queue_url) = match (self.client.as_ref(), self.queue_url.as_ref()) {
+            (Some(client), Some(queue_url)) => (client, queue_url),
+            _ => return Err(BidError::Publisher("Failed to get sqs client".to_string())),
+        };
+
+        let message = BidMessage {
+            id,
+            cid,
+            payload: &bid.payload,
+            parsed_values: &bid.parsed_values,
+            received_time: &bid.received_time,
+        };
+        let message_body = match serde_json::to_string(&message) {
+            Ok(message_body) => message_body,
+            Err(e) => return Err(BidError::Publisher(e.to_string())),
+        };
+        let request = SendMessageRequest {
+            queue_url: queue_url.clone(),
+            message_body,
+            ..Default::default()
+        };
+        match client.send_message(request).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(BidError::Publisher(e.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Connectable for SqsPublisher {
+    async fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    async fn ping(&mut self) -> Result<(), BidError> {
+        let (client, queue_url) = match (self.client.as_ref(), self.queue_url.as_ref()) {
+            (Some(client), Some(queue_url)) => (client, queue_url),
+            _ => return Err(BidError::Publisher("Failed to get sqs client".to_string())),
+        };
+        let request = GetQueueAttributesRequest {
+            queue_url: queue_url.clone(),
+            attribute_names: Some(vec!["QueueArn".to_string()]),
+        };
+        match client.get_queue_attributes(request).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(BidError::Publisher(e.to_string())),
+        }
+    }
+
+    async fn connect(&mut self) -> Result<(), BidError> {
+        let queue_url = get_env_var("SQS_QUEUE_URL").map_err(BidError::Publisher)?;
+        // SQS_ENDPOINT points the client at a local SQS stand-in during development
+        let region = match env::var("SQS_ENDPOINT") {
+            Ok(endpoint) => Region::Custom {
+                name: Region::default().name().to_string(),
+                endpoint,
+            },
+            Err(_) => Region::default(),
+        };
+        self.client = Some(SqsClient::new(region));
+        self.queue_url = Some(queue_url);
+        self.ping().await
+    }
+}
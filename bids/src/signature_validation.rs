@@ -1,17 +1,55 @@
-use ethers::types::{Address, Signature, H256};
-use std::str::FromStr;
+use crate::error::BidError;
+use ethers::types::{Address, Signature, H256, U256};
+
+// secp256k1n / 2. Any larger s has a twin signature with s' = n - s, so only
+// the low-s one is accepted (EIP-2)
+const SECP256K1N_HALF: U256 = U256([
+    0xdfe92f46681b20a0,
+    0x5d576e7357a4501d,
+    0xffffffffffffffff,
+    0x7fffffffffffffff,
+]);
+
+/// Parses a 65-byte `r || s || v` signature or a 64-byte EIP-2098 compact
+/// `r || yParityAndS` signature, rejecting high-s signatures
+pub fn parse_signature(signature: &str) -> Result<Signature, BidError> {
+    let bytes = match hex::decode(signature.strip_prefix("0x").unwrap_or(signature)) {
+        Ok(bytes) => bytes,
+        Err(_) => return Err(BidError::InvalidSignature),
+    };
+    let sig = match bytes.len() {
+        65 => match Signature::try_from(bytes.as_slice()) {
+            Ok(sig) => sig,
+            Err(_) => return Err(BidError::InvalidSignature),
+        },
+        // The top bit of s is always clear in a low-s signature, so EIP-2098
+        // stores the y parity there
+        64 => {
+            let y_parity = bytes[32] >> 7;
+            let mut s = bytes[32..].to_vec();
+            s[0] &= 0x7f;
+            Signature {
+                r: U256::from_big_endian(&bytes[..32]),
+                s: U256::from_big_endian(&s),
+                v: 27 + y_parity as u64,
+            }
+        }
+        _ => return Err(BidError::InvalidSignature),
+    };
+    if sig.s > SECP256K1N_HALF {
+        return Err(BidError::NonCanonicalSignature);
+    }
+    Ok(sig)
+}
 
 pub fn verify_signature(
     signer: Address,
     typed_data_hash_bytes: [u8; 32],
     signature: &String,
-) -> Result<Signature, String> {
-    let sig = match Signature::from_str(signature) {
-        Ok(sig) => sig,
-        Err(_) => return Err("Invalid signature".to_string()),
-    };
+) -> Result<Signature, BidError> {
+    let sig = parse_signature(signature)?;
     match sig.verify(H256(typed_data_hash_bytes), signer) {
         Ok(_) => Ok(sig),
-        Err(_) => Err("Signature does not match signer".to_string()),
+        Err(_) => Err(BidError::SignatureMismatch),
     }
 }
@@ -1,7 +1,9 @@
+use crate::error::BidError;
 use async_trait::async_trait;
 use mockall::{automock, predicate::*};
 use std::env;
-use tokio::sync::{Mutex, MutexGuard};
+use std::ops::{Deref, DerefMut};
+use tokio::sync::{Mutex, MutexGuard, Semaphore, SemaphorePermit};
 
 pub fn get_env_var(name: &str) -> Result<String, String> {
     env::var(name).map_err(|_| format!("env var \"{}\" not set", name))
@@ -11,36 +13,92 @@ pub fn get_env_var(name: &str) -> Result<String, String> {
 #[async_trait]
 pub trait Connectable {
     async fn is_connected(&self) -> bool;
-    async fn connect(&mut self) -> Result<(), String>;
-    async fn ping(&mut self) -> Result<(), String>;
+    async fn connect(&mut self) -> Result<(), BidError>;
+    async fn ping(&mut self) -> Result<(), BidError>;
 }
 
-pub async fn lock_connectable_mutex_safely<T: Connectable>(
-    mutex: &Mutex<T>,
-) -> Result<MutexGuard<T>, String> {
-    let mut mutex_guard = match mutex.try_lock() {
-        Ok(mutex_guard) => mutex_guard,
-        Err(_) => return Err("Failed to lock mutex".to_string()),
-    };
-    if !mutex_guard.is_connected().await {
+/// Fixed set of lazily connected `Connectable`s. `get` waits for an idle
+/// connection rather than failing while every connection is in use.
+pub struct ConnectionPool<T> {
+    connections: Vec<Mutex<T>>,
+    idle: Semaphore,
+}
+
+pub struct PooledConnection<'a, T> {
+    // Declared before the permit so the connection is unlocked before a
+    // waiter is woken
+    guard: MutexGuard<'a, T>,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl<T> Deref for PooledConnection<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for PooledConnection<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T: Connectable> ConnectionPool<T> {
+    pub fn new(connections: Vec<T>) -> Self {
+        assert!(!connections.is_empty(), "ConnectionPool needs a connection");
+        let idle = Semaphore::new(connections.len());
+        ConnectionPool {
+            connections: connections.into_iter().map(Mutex::new).collect(),
+            idle,
+        }
+    }
+
+    pub async fn get(&self) -> Result<PooledConnection<'_, T>, BidError> {
+        let permit = match self.idle.acquire().await {
+            Ok(permit) => permit,
+            Err(e) => return Err(BidError::ConnectionPool(e.to_string())),
+        };
+        // Holding a permit guarantees at least one connection is unlocked
+        let guard = match self.connections.iter().find_map(|c| c.try_lock().ok()) {
+            Some(guard) => guard,
+            None => {
+                return Err(BidError::ConnectionPool(
+                    "No idle connection in pool".to_string(),
+                ))
+            }
+        };
+        let mut connection = PooledConnection {
+            guard,
+            _permit: permit,
+        };
+        ensure_connected(&mut *connection).await?;
+
+        Ok(connection)
+    }
+}
+
+async fn ensure_connected<T: Connectable>(connection: &mut T) -> Result<(), BidError> {
+    if !connection.is_connected().await {
         println!("Establishing new connection...");
-        match mutex_guard.connect().await {
+        match connection.connect().await {
             Ok(_) => (),
-            Err(e) => return Err(e.to_string()),
+            Err(e) => return Err(e),
         };
     } else {
         println!("Reusing connection ⚡");
-        match mutex_guard.ping().await {
+        match connection.ping().await {
             Ok(_) => (),
             Err(e) => {
                 eprintln!("Ping failed: {}. Attempting to reconnect...", e.to_string());
-                match mutex_guard.connect().await {
+                match connection.connect().await {
                     Ok(_) => (),
-                    Err(e) => return Err(e.to_string()),
+                    Err(e) => return Err(e),
                 };
             }
         }
     };
 
-    Ok(mutex_guard)
+    Ok(())
 }
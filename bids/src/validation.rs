@@ -0,0 +1,109 @@
+use crate::error::{BidError, ProblemDetails};
+use serde::Serialize;
+
+/// Checks run on every Bid, in the order they are run
+pub const BID_CHECKS: [&str; 13] = [
+    "schema",
+    "chain",
+    "signer",
+    "auction_address",
+    "signature",
+    "bidder",
+    "auction",
+    "base_price",
+    "auction_window",
+    "deadline",
+    "nonce",
+    "approval",
+    "balance",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckOutcome {
+    pub check: &'static str,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<ProblemDetails>,
+}
+
+/// Records the outcome of each check as a Bid is validated. Checks that others
+/// don't depend on keep validation running, so every failure is reported at once.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    outcomes: Vec<CheckOutcome>,
+    errors: Vec<BidError>,
+}
+
+impl ValidationReport {
+    pub fn pass(&mut self, check: &'static str) {
+        self.outcomes.push(CheckOutcome {
+            check,
+            status: CheckStatus::Passed,
+            problem: None,
+        });
+    }
+
+    /// Records a check whose failure ends validation, returning every failure
+    /// recorded so far
+    pub fn fail(&mut self, check: &'static str, error: BidError) -> BidError {
+        self.outcomes.push(CheckOutcome {
+            check,
+            status: CheckStatus::Failed,
+            problem: Some(ProblemDetails::from(&error)),
+        });
+        self.errors.push(error);
+        self.error()
+    }
+
+    /// Records a check that later checks don't depend on, yielding `None` when
+    /// it fails. `finish` ends validation once these checks have run.
+    pub fn check<T>(&mut self, check: &'static str, result: Result<T, BidError>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.pass(check);
+                Some(value)
+            }
+            Err(e) => {
+                self.fail(check, e);
+                None
+            }
+        }
+    }
+
+    /// Ends validation if any check has failed so far
+    pub fn finish(&self) -> Result<(), BidError> {
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(self.error()),
+        }
+    }
+
+    fn error(&self) -> BidError {
+        match self.errors.as_slice() {
+            [error] => error.clone(),
+            errors => BidError::Multiple(errors.to_vec()),
+        }
+    }
+
+    /// Every check in `checks`, with the ones that never ran marked as skipped
+    pub fn into_outcomes(mut self, checks: &[&'static str]) -> Vec<CheckOutcome> {
+        for check in checks {
+            if !self.outcomes.iter().any(|outcome| outcome.check == *check) {
+                self.outcomes.push(CheckOutcome {
+                    check,
+                    status: CheckStatus::Skipped,
+                    problem: None,
+                });
+            }
+        }
+        self.outcomes
+    }
+}
@@ -1,16 +1,37 @@
 use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use ethers::providers::{Http, Provider};
 use ethers::types::{Address, U256};
 use lambda_http::http::{Method, StatusCode};
-use lambda_http::{Body, Request};
+use lambda_http::{Body, Request, RequestExt};
 use mockall::{mock, predicate::*};
 use pikapool_api::auction::Auction;
 use pikapool_api::bid::Bid;
-use pikapool_api::cache::Cache as RealCache;
-use pikapool_api::core::put_request_handler;
-use pikapool_api::database::Database as RealDatabase;
+use pikapool_api::bid_payload::Deadline;
+use pikapool_api::cache::{BidContext, Cache as RealCache, KeySchema, RedisCache};
+use pikapool_api::chain_registry::{ChainConfig, ChainRegistry};
+use pikapool_api::contract_wallet::{
+    ContractWalletVerifier as RealContractWalletVerifier, ProviderVerifier,
+};
+use pikapool_api::core::{
+    batch_put_request_handler, delete_request_handler, get_request_handler, put_request_handler,
+    request_handler, validate_request_handler,
+};
+use pikapool_api::database::{
+    BidQuery, BidRecord, Database as RealDatabase, RdsProvider, StoredBid,
+};
 use pikapool_api::dummy_data;
-use pikapool_api::utils::Connectable;
-use serde_json::to_string;
+use pikapool_api::error::BidError;
+use pikapool_api::publisher::Publisher as RealPublisher;
+use pikapool_api::signature_validation::verify_signature;
+use pikapool_api::utils::{Connectable, ConnectionPool, MockConnectable};
+use redis::aio::ConnectionManager;
+use serde_json::{from_str, to_string, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::Mutex;
 
 mock! {
@@ -18,14 +39,57 @@ mock! {
 
     #[async_trait]
     impl Connectable for Database {
-        async fn connect(&mut self) -> Result<(), String>;
-        async fn ping(&mut self) -> Result<(), String>;
+        async fn connect(&mut self) -> Result<(), BidError>;
+        async fn ping(&mut self) -> Result<(), BidError>;
         async fn is_connected(&self) -> bool;
     }
 
     #[async_trait]
     impl RealDatabase for Database {
-        async fn insert_bid(&mut self, bid: &Bid) -> Result<String, String>;
+        async fn insert_bid(&mut self, bid: &Bid) -> Result<StoredBid, BidError>;
+        async fn insert_bids(
+            &mut self,
+            bids: &[Bid],
+        ) -> Result<Vec<Result<StoredBid, BidError>>, BidError>;
+        async fn get_bid(&mut self, bid_id: &str) -> Result<Option<BidRecord>, BidError>;
+        async fn list_bids(&mut self, query: &BidQuery) -> Result<Vec<BidRecord>, BidError>;
+        async fn cancel_bid(
+            &mut self,
+            bid_id: &str,
+            auction_address: &Address,
+            auction_name: &str,
+            signer: &Address,
+        ) -> Result<Option<String>, BidError>;
+        async fn get_signer_exposure(
+            &mut self,
+            signer: &Address,
+            settlement_contract: &Address,
+            auction_address: &Address,
+            auction_name: &str,
+        ) -> Result<U256, BidError>;
+        async fn get_highest_nonce(
+            &mut self,
+            signer: &Address,
+            auction_address: &Address,
+            auction_name: &str,
+            excluded_bid_id: &str,
+        ) -> Result<Option<U256>, BidError>;
+    }
+}
+
+mock! {
+    Publisher {}
+
+    #[async_trait]
+    impl Connectable for Publisher {
+        async fn connect(&mut self) -> Result<(), BidError>;
+        async fn ping(&mut self) -> Result<(), BidError>;
+        async fn is_connected(&self) -> bool;
+    }
+
+    #[async_trait]
+    impl RealPublisher for Publisher {
+        async fn publish_bid(&mut self, bid: &Bid, id: &str, cid: &str) -> Result<(), BidError>;
     }
 }
 
@@ -34,33 +98,84 @@ mock! {
 
     #[async_trait]
     impl Connectable for Cache {
-        async fn connect(&mut self) -> Result<(), String>;
-        async fn ping(&mut self) -> Result<(), String>;
+        async fn connect(&mut self) -> Result<(), BidError>;
+        async fn ping(&mut self) -> Result<(), BidError>;
         async fn is_connected(&self) -> bool;
     }
 
     #[async_trait]
     impl RealCache for Cache {
-        fn get_signer_approve_and_bal_amts(
+        async fn get_signer_approve_and_bal_amts(
             &mut self,
             chain_id: &str,
             verifying_contract: &Address,
             signer: &Address,
-        ) -> Result<Option<(U256, U256)>, String>;
-        fn get_auction(
+        ) -> Result<Option<(U256, U256)>, BidError>;
+        async fn get_auction(
             &mut self,
             chain_id: &str,
             auction_contract: &Address,
             auction_name: &str,
-        ) -> Result<Option<Auction>, String>;
-        fn get_synced_block(
+        ) -> Result<Option<Auction>, BidError>;
+        async fn get_synced_block(
+            &mut self,
+            chain_id: &str,
+            settlement_contract: &Address,
+        ) -> Result<u64, BidError>;
+        async fn get_synced_timestamp(
             &mut self,
             chain_id: &str,
             settlement_contract: &Address,
-        ) -> Result<u64, String>;
+        ) -> Result<Option<u64>, BidError>;
+        async fn get_bid_context(
+            &mut self,
+            chain_id: &str,
+            auction_contract: &Address,
+            auction_name: &str,
+            settlement_contract: &Address,
+            signer: &Address,
+        ) -> Result<BidContext, BidError>;
+    }
+}
+
+mock! {
+    Verifier {}
+
+    #[async_trait]
+    impl Connectable for Verifier {
+        async fn connect(&mut self) -> Result<(), BidError>;
+        async fn ping(&mut self) -> Result<(), BidError>;
+        async fn is_connected(&self) -> bool;
+    }
+
+    #[async_trait]
+    impl RealContractWalletVerifier for Verifier {
+        async fn is_contract_wallet(
+            &mut self,
+            chain_id: &str,
+            wallet: &Address,
+        ) -> Result<bool, BidError>;
+        async fn is_valid_signature(
+            &mut self,
+            chain_id: &str,
+            wallet: &Address,
+            hash: [u8; 32],
+            signature: &[u8],
+        ) -> Result<bool, BidError>;
     }
 }
 
+fn new_list_request(params: &[(&str, &str)]) -> Request {
+    let mut r = Request::default();
+    *r.method_mut() = Method::GET;
+    *r.uri_mut() = "/v0/bids".parse().unwrap();
+    let params: HashMap<String, String> = params
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    r.with_query_string_parameters(params)
+}
+
 async fn with_lock<T, F, R>(mutex: &Mutex<T>, f: F) -> R
 where
     F: FnOnce(&mut T) -> R,
@@ -69,6 +184,222 @@ where
     f(&mut *guard)
 }
 
+fn pool<T: Connectable>(mutex: Mutex<T>) -> ConnectionPool<T> {
+    ConnectionPool::new(vec![mutex.into_inner()])
+}
+
+// No signer is a contract wallet, so only ECDSA signatures are accepted
+fn no_contract_wallets() -> ConnectionPool<MockVerifier> {
+    let mut verifier = MockVerifier::new();
+    verifier.expect_is_connected().returning(|| true);
+    verifier.expect_ping().returning(|| Ok(()));
+    verifier
+        .expect_is_contract_wallet()
+        .returning(|_, _| Ok(false));
+    pool(Mutex::new(verifier))
+}
+
+/// In-memory stand-in for the Redis commands the cache sends, served over RESP
+#[derive(Debug, Clone, Default)]
+struct FakeRedis {
+    strings: HashMap<String, String>,
+    hashes: HashMap<String, HashMap<String, String>>,
+}
+
+impl FakeRedis {
+    fn with_hash(mut self, key: &str, fields: &[(&str, &str)]) -> Self {
+        let fields = fields
+            .iter()
+            .map(|(field, value)| (field.to_string(), value.to_string()))
+            .collect();
+        self.hashes.insert(key.to_string(), fields);
+        self
+    }
+
+    fn with_string(mut self, key: &str, value: &str) -> Self {
+        self.strings.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    fn reply(&self, command: &[String]) -> String {
+        let array = |values: Vec<Option<&String>>| {
+            values.iter().fold(
+                format!("*{}\r\n", values.len()),
+                |reply, value| match value {
+                    Some(value) => reply + &format!("${}\r\n{}\r\n", value.len(), value),
+                    None => reply + "$-1\r\n",
+                },
+            )
+        };
+        match command[0].to_uppercase().as_str() {
+            "PING" => "+PONG\r\n".to_string(),
+            "MGET" => array(
+                command[1..]
+                    .iter()
+                    .map(|key| self.strings.get(key))
+                    .collect(),
+            ),
+            "HMGET" => {
+                let hash = self.hashes.get(&command[1]);
+                array(
+                    command[2..]
+                        .iter()
+                        .map(|field| hash.and_then(|hash| hash.get(field)))
+                        .collect(),
+                )
+            }
+            _ => "-ERR unknown command\r\n".to_string(),
+        }
+    }
+
+    /// Serves the fake on a local port and connects a cache to it
+    async fn connect(self, key_schema: KeySchema) -> RedisCache {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("redis://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(self.clone().serve(socket));
+            }
+        });
+        let client = redis::Client::open(url).unwrap();
+        RedisCache {
+            connection: Some(ConnectionManager::new(client).await.unwrap()),
+            key_schema,
+        }
+    }
+
+    async fn serve(self, socket: TcpStream) {
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(reader);
+        while let Some(command) = read_command(&mut reader).await {
+            if writer
+                .write_all(self.reply(&command).as_bytes())
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+// Reads a RESP array of bulk strings, or None once the connection is closed
+async fn read_command(reader: &mut BufReader<OwnedReadHalf>) -> Option<Vec<String>> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+    let mut command = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let len: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0; len + 2];
+        reader.read_exact(&mut arg).await.ok()?;
+        arg.truncate(len);
+        command.push(String::from_utf8(arg).ok()?);
+    }
+    Some(command)
+}
+
+/// Serves one JSON-RPC response body on a local port, for a provider to call
+async fn fake_rpc(response: &'static str) -> Provider<Http> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut content_length = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 2 {
+            if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                content_length = length.trim().parse().unwrap();
+            }
+            line.clear();
+        }
+        let mut request = vec![0; content_length];
+        reader.read_exact(&mut request).await.unwrap();
+        let reply = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            response.len(),
+            response
+        );
+        writer.write_all(reply.as_bytes()).await.unwrap();
+    });
+    Provider::<Http>::try_from(url).unwrap()
+}
+
+const AUCTION_KEY: &str =
+    "v2:1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale";
+const SYNCED_BLOCK_KEY: &str =
+    "v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:syncedBlock";
+const SYNCED_TIMESTAMP_KEY: &str =
+    "v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:syncedTimestamp";
+const SIGNER_DETAILS_KEY: &str = "v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:signer:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+// What chain-state-service caches for the Valid Bid, with the auction hash
+// missing `missing_field`
+fn fake_redis_without(missing_field: &str) -> FakeRedis {
+    let auction_fields: Vec<(&str, &str)> = [
+        ("startBlock", "100"),
+        ("endBlock", "200"),
+        (
+            "settlementContract",
+            "0xd2090025857B9C7B24387741f120538E928A3a59",
+        ),
+        ("basePrice", "250000000000000000"),
+    ]
+    .into_iter()
+    .filter(|(field, _)| *field != missing_field)
+    .collect();
+    FakeRedis::default()
+        .with_hash(AUCTION_KEY, &auction_fields)
+        .with_string(SYNCED_BLOCK_KEY, "150")
+        .with_string(SYNCED_TIMESTAMP_KEY, "1700000000")
+        .with_hash(
+            SIGNER_DETAILS_KEY,
+            &[
+                ("approveValue", "MAX_INT256"),
+                ("balanceValue", "200000000000000000000"),
+            ],
+        )
+}
+
+// Mainnet with its settlement contract and the given bidding margins
+fn registry_with_margins(start_grace_blocks: u64, end_margin_blocks: u64) -> ChainRegistry {
+    ChainRegistry::new(HashMap::from([(
+        "1".to_string(),
+        ChainConfig {
+            settlement_contracts: vec![Address::from_str(
+                "0xd2090025857B9C7B24387741f120538E928A3a59",
+            )
+            .unwrap()],
+            domain_name: "Pikapool Auction".to_string(),
+            domain_versions: vec!["1".to_string()],
+            max_sync_age: 60,
+            start_grace_blocks,
+            end_margin_blocks,
+        },
+    )]))
+}
+
+fn v2_keys() -> KeySchema {
+    KeySchema {
+        prefix: String::new(),
+        read_legacy: false,
+    }
+}
+
+/// Connects to the postgres database configured through the RDS_* env vars.
+/// The tests using it are ignored by default, run them with
+/// `cargo test -- --ignored`
+async fn connected_rds_provider() -> RdsProvider {
+    dotenv::dotenv().ok();
+    let mut db = RdsProvider { client: None };
+    db.connect().await.unwrap();
+    db
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -79,13 +410,23 @@ mod tests {
         let mock_db = Mutex::new(MockDatabase::new());
         let mut r = Request::default();
         *r.method_mut() = Method::PUT;
-        let response = put_request_handler(r, &mock_cache, &mock_db).await.unwrap();
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Request body missing\"}"
+                "{\"type\":\"urn:pikapool:error:request-body-missing\",\"title\":\"Request body missing\",\"status\":400,\"detail\":\"Request body missing\",\"code\":\"REQUEST_BODY_MISSING\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -95,17 +436,25 @@ mod tests {
     async fn request_handler_invalid_eip712() {
         let mut r = Request::new(Body::from("invalid body"));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"expected value at line 1 column 1\"}"
+                "{\"type\":\"urn:pikapool:error:malformed-payload\",\"title\":\"Malformed payload\",\"status\":400,\"detail\":\"expected value at line 1 column 1\",\"code\":\"MALFORMED_PAYLOAD\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -116,17 +465,25 @@ mod tests {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::InvalidBid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"typed_data is not a valid Pikapool Bid\"}"
+                "{\"type\":\"urn:pikapool:error:invalid-typed-data\",\"title\":\"Invalid typed_data\",\"status\":400,\"detail\":\"typed_data is not a valid Pikapool Bid: invalid typed_data.domain.name\",\"code\":\"INVALID_TYPED_DATA\",\"fields\":[\"typed_data.domain.name\"]}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -138,17 +495,25 @@ mod tests {
             dummy_data::new_bid_payload(dummy_data::BidPayloadOption::BadSignerAddress);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Invalid signer address\"}"
+                "{\"type\":\"urn:pikapool:error:invalid-signer-address\",\"title\":\"Invalid signer address\",\"status\":400,\"detail\":\"Invalid signer address\",\"code\":\"INVALID_SIGNER_ADDRESS\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -160,17 +525,25 @@ mod tests {
             dummy_data::new_bid_payload(dummy_data::BidPayloadOption::InvalidAuctionAddress);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Invalid auction contract address\"}"
+                "{\"type\":\"urn:pikapool:error:invalid-auction-address\",\"title\":\"Invalid auction contract address\",\"status\":400,\"detail\":\"Invalid auction contract address\",\"code\":\"INVALID_AUCTION_ADDRESS\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -182,39 +555,221 @@ mod tests {
             dummy_data::new_bid_payload(dummy_data::BidPayloadOption::InvalidSignature);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Invalid signature\"}"
+                "{\"type\":\"urn:pikapool:error:invalid-signature\",\"title\":\"Invalid signature\",\"status\":400,\"detail\":\"Invalid signature\",\"code\":\"INVALID_SIGNATURE\"}"
             ),
             _ => panic!("Malformed response"),
         }
     }
 
+    #[tokio::test]
+    async fn request_handler_unsupported_chain() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        // The cache has no expectations, so fetching from it would panic
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &ChainRegistry::new(HashMap::new()),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:unsupported-chain\",\"title\":\"Chain is not supported\",\"status\":400,\"detail\":\"Chain 1 is not supported\",\"code\":\"UNSUPPORTED_CHAIN\",\"chain_id\":\"1\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_unsupported_settlement_contract() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let registry = ChainRegistry::new(HashMap::from([(
+            "1".to_string(),
+            ChainConfig {
+                settlement_contracts: vec![Address::random()],
+                domain_name: "Pikapool Auction".to_string(),
+                domain_versions: vec!["1".to_string()],
+                max_sync_age: 60,
+                start_grace_blocks: 0,
+                end_margin_blocks: 0,
+            },
+        )]));
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &registry,
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:unsupported-settlement-contract\",\"title\":\"Settlement contract is not supported on this chain\",\"status\":400,\"detail\":\"Settlement contract 0xd2090025857b9c7b24387741f120538e928a3a59 is not supported on chain 1\",\"code\":\"UNSUPPORTED_SETTLEMENT_CONTRACT\",\"chain_id\":\"1\",\"settlement_contract\":\"0xd2090025857b9c7b24387741f120538e928a3a59\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_unexpected_domain_version() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let registry = ChainRegistry::new(HashMap::from([(
+            "1".to_string(),
+            ChainConfig {
+                settlement_contracts: vec![Address::from_str(
+                    "0xd2090025857B9C7B24387741f120538E928A3a59",
+                )
+                .unwrap()],
+                domain_name: "Pikapool Auction".to_string(),
+                domain_versions: vec!["2".to_string()],
+                max_sync_age: 60,
+                start_grace_blocks: 0,
+                end_margin_blocks: 0,
+            },
+        )]));
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &registry,
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:invalid-typed-data\",\"title\":\"Invalid typed_data\",\"status\":400,\"detail\":\"typed_data is not a valid Pikapool Bid: invalid typed_data.domain.version\",\"code\":\"INVALID_TYPED_DATA\",\"fields\":[\"typed_data.domain.version\"]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
     #[tokio::test]
     async fn request_handler_sig_doesnt_match_signer() {
         let bid_payload =
             dummy_data::new_bid_payload(dummy_data::BidPayloadOption::SignatureDoesNotMatchSigner);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:signature-mismatch\",\"title\":\"Signature does not match signer\",\"status\":400,\"detail\":\"Signature does not match signer\",\"code\":\"SIGNATURE_MISMATCH\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_bidder_doesnt_match_signer() {
+        let bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::BidderDoesNotMatchSigner);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:bidder-mismatch\",\"title\":\"Bidder does not match signer\",\"status\":400,\"detail\":\"Bidder does not match signer\",\"code\":\"BIDDER_MISMATCH\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_sender_claims_bidder() {
+        let mut bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::BidderDoesNotMatchSigner);
+        // Claiming to be the named bidder doesn't help, the signature is from someone else
+        bid_payload.sender = "0x36bCaEE2F1f6C185f91608C7802f6Fc4E8bD9f1d".to_string();
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Signature does not match signer\"}"
+                "{\"type\":\"urn:pikapool:error:signature-mismatch\",\"title\":\"Signature does not match signer\",\"status\":400,\"detail\":\"Signature does not match signer\",\"code\":\"SIGNATURE_MISMATCH\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -225,26 +780,41 @@ mod tests {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
             cache.expect_is_connected().returning(|| true);
-            cache.expect_get_auction().returning(|_, _, _| Ok(None));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: None,
+                    synced_block: None,
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: None,
+                })
+            });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Specified auction does not exist\"}"
+                "{\"type\":\"urn:pikapool:error:auction-not-found\",\"title\":\"Specified auction does not exist\",\"status\":400,\"detail\":\"Specified auction does not exist\",\"code\":\"AUCTION_NOT_FOUND\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -255,30 +825,43 @@ mod tests {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
             cache.expect_is_connected().returning(|| true);
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::InvalidSettlementAddress,
-                )))
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(
+                        dummy_data::AuctionOption::InvalidSettlementAddress,
+                    )),
+                    synced_block: None,
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: None,
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Specified settlement contract does not match auction settlement contract\"}"
+                "{\"type\":\"urn:pikapool:error:settlement-contract-mismatch\",\"title\":\"Specified settlement contract does not match auction settlement contract\",\"status\":400,\"detail\":\"Specified settlement contract does not match auction settlement contract\",\"code\":\"SETTLEMENT_CONTRACT_MISMATCH\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"expected\":\"0xaaa90025857b9c7b24387741f120538e928a3a59\",\"provided\":\"0xd2090025857b9c7b24387741f120538e928a3a59\"}"
             ),
             _ => panic!("Malformed response"),
         }
@@ -289,287 +872,3540 @@ mod tests {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
             cache.expect_is_connected().returning(|| true);
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::InvalidBasePrice,
-                )))
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(
+                        dummy_data::AuctionOption::InvalidBasePrice,
+                    )),
+                    synced_block: Some(100),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Specified base_price does not match auction base_price\"}"
+                "{\"type\":\"urn:pikapool:error:base-price-mismatch\",\"title\":\"Specified base_price does not match auction base_price\",\"status\":400,\"detail\":\"Specified base_price 250000000000000000 does not match auction base_price 1250000000000000000\",\"code\":\"BASE_PRICE_MISMATCH\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"expected\":\"1250000000000000000\",\"provided\":\"250000000000000000\"}"
             ),
             _ => panic!("Malformed response"),
         }
     }
 
     #[tokio::test]
-    async fn request_handler_auction_not_started() {
+    async fn request_handler_reports_independent_failures_together() {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
-            cache.expect_is_connected().returning(|| true);
+        with_lock(&mock_cache, |cache| {
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
-            cache
-                .expect_get_signer_approve_and_bal_amts()
-                .returning(|_, _, _| {
-                    Ok(Some((
-                        200000000000000000000u128.into(),
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(
+                        dummy_data::AuctionOption::InvalidBasePrice,
+                    )),
+                    synced_block: Some(100),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
                         200000000000000000000u128.into(),
-                    )))
-                });
-            cache.expect_get_synced_block().returning(|_, _| Ok(99));
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::Valid,
-                )))
+                        100000000000000000u128.into(),
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
-            Body::Text(msg) => assert_eq!(
-                msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Auction has not started\"}"
-            ),
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:multiple-errors\",\"title\":\"Bid failed multiple checks\",\"status\":400,\"detail\":\"Specified base_price 250000000000000000 does not match auction base_price 1250000000000000000; Bid costs 1750000000000000000 but the signer balance is 100000000000000000\",\"code\":\"MULTIPLE_ERRORS\",\"errors\":[{\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"code\":\"BASE_PRICE_MISMATCH\",\"detail\":\"Specified base_price 250000000000000000 does not match auction base_price 1250000000000000000\",\"expected\":\"1250000000000000000\",\"provided\":\"250000000000000000\",\"status\":400,\"title\":\"Specified base_price does not match auction base_price\",\"type\":\"urn:pikapool:error:base-price-mismatch\"},{\"available\":\"100000000000000000\",\"code\":\"INSUFFICIENT_BALANCE\",\"committed\":\"0\",\"detail\":\"Bid costs 1750000000000000000 but the signer balance is 100000000000000000\",\"required\":\"1750000000000000000\",\"status\":403,\"title\":\"Signer token balance is insufficient\",\"type\":\"urn:pikapool:error:insufficient-balance\"}]}"),
             _ => panic!("Malformed response"),
         }
     }
 
     #[tokio::test]
-    async fn request_handler_auction_has_ended() {
+    async fn request_handler_auction_not_started() {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_is_connected().returning(|| true);
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
-            cache
-                .expect_get_signer_approve_and_bal_amts()
-                .returning(|_, _, _| {
-                    Ok(Some((
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(99),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
                         200000000000000000000u128.into(),
                         200000000000000000000u128.into(),
-                    )))
-                });
-            cache.expect_get_synced_block().returning(|_, _| Ok(201));
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::Valid,
-                )))
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers()["Content-Type"],
+            "application/problem+json"
+        );
         match response.body() {
             Body::Text(msg) => assert_eq!(
                 msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Auction has ended\"}"
+                "{\"type\":\"urn:pikapool:error:auction-not-started\",\"title\":\"Auction has not started\",\"status\":400,\"detail\":\"Auction starts at block 100 but the latest synced block is 99\",\"code\":\"AUCTION_NOT_STARTED\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"start_block\":100,\"synced_block\":99}"
             ),
             _ => panic!("Malformed response"),
         }
     }
 
     #[tokio::test]
-    async fn request_handler_no_signer_approval() {
+    async fn request_handler_auction_has_ended() {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_is_connected().returning(|| true);
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
-            cache
-                .expect_get_signer_approve_and_bal_amts()
-                .returning(|_, _, _| Ok(None));
-            cache.expect_get_synced_block().returning(|_, _| Ok(150));
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::Valid,
-                )))
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(201),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
-        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
-            Body::Text(msg) => assert_eq!(msg, "{\"id\":null,\"cid\":null,\"error\":\"Signer has not approved the settlement contract\"}"),
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:auction-ended\",\"title\":\"Auction has ended\",\"status\":400,\"detail\":\"Auction ended at block 200 but the latest synced block is 201\",\"code\":\"AUCTION_ENDED\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"end_block\":200,\"synced_block\":201}"
+            ),
             _ => panic!("Malformed response"),
         }
     }
 
     #[tokio::test]
-    async fn request_handler_approval_too_low() {
+    async fn request_handler_bid_deadline_block_passed() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::DeadlineBlock);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(151),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:bid-expired\",\"title\":\"Bid has expired\",\"status\":400,\"detail\":\"Bid expired at block 150 but the latest synced block is 151\",\"code\":\"BID_EXPIRED\",\"deadline_block\":150,\"synced_block\":151}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_bid_deadline_timestamp_passed() {
+        let bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::DeadlineTimestamp);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // The received timestamp is the current time
+        let problem: Value = match response.body() {
+            Body::Text(msg) => from_str(msg).unwrap(),
+            _ => panic!("Malformed response"),
+        };
+        assert_eq!(problem["code"], "BID_EXPIRED");
+        assert_eq!(problem["deadline_timestamp"], 1600000000);
+        assert!(problem["received_timestamp"].as_u64().unwrap() > 1600000000);
+    }
+
+    #[tokio::test]
+    async fn request_handler_chain_state_stale() {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let synced_timestamp = dummy_data::new_synced_timestamp() - 61;
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_is_connected().returning(|| true);
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
             cache
-                .expect_get_signer_approve_and_bal_amts()
-                .returning(|_, _, _| {
-                    Ok(Some((
-                        500000000000000000u128.into(),
-                        100000000000000000u128.into(),
-                    )))
+                .expect_get_bid_context()
+                .returning(move |_, _, _, _, _| {
+                    // Not synced yet rather than missing
+                    Ok(BidContext {
+                        auction: None,
+                        synced_block: Some(150),
+                        synced_timestamp: Some(synced_timestamp),
+                        signer_approve_and_bal_amts: Some((
+                            200000000000000000000u128.into(),
+                            200000000000000000000u128.into(),
+                        )),
+                    })
                 });
-            cache.expect_get_synced_block().returning(|_, _| Ok(150));
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::Valid,
-                )))
+        })
+        .await;
+
+        // Nothing is read from the database once the chain state is stale
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let problem: Value = match response.body() {
+            Body::Text(msg) => from_str(msg).unwrap(),
+            _ => panic!("Malformed response"),
+        };
+        assert_eq!(problem["code"], "CHAIN_STATE_STALE");
+        assert_eq!(problem["synced_timestamp"], synced_timestamp);
+        assert_eq!(problem["max_sync_age"], 60);
+        assert!(problem["received_timestamp"].as_u64().unwrap() > synced_timestamp + 60);
+    }
+
+    #[tokio::test]
+    async fn request_handler_chain_state_without_sync_time() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache
+                .expect_get_bid_context()
+                .returning(move |_, _, _, _, _| {
+                    Ok(BidContext {
+                        auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                        synced_block: Some(150),
+                        synced_timestamp: None,
+                        signer_approve_and_bal_amts: Some((
+                            200000000000000000000u128.into(),
+                            200000000000000000000u128.into(),
+                        )),
+                    })
+                });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let problem: Value = match response.body() {
+            Body::Text(msg) => from_str(msg).unwrap(),
+            _ => panic!("Malformed response"),
+        };
+        assert_eq!(problem["code"], "CHAIN_STATE_STALE");
+        assert_eq!(problem["detail"], "Chain state has no sync time");
+        assert_eq!(problem["synced_timestamp"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn request_handler_bidding_not_open() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(102),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &registry_with_margins(5, 0),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
-        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
-            Body::Text(msg) => assert_eq!(
-                msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Signer approval amount is insufficient\"}"
-            ),
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:bidding-not-open\",\"title\":\"Bidding has not opened\",\"status\":400,\"detail\":\"Bidding opens at block 105 but the latest synced block is 102\",\"code\":\"BIDDING_NOT_OPEN\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"open_block\":105,\"synced_block\":102}"),
             _ => panic!("Malformed response"),
         }
     }
 
     #[tokio::test]
-    async fn request_handler_balance_too_low() {
+    async fn request_handler_bidding_closed() {
         let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_is_connected().returning(|| true);
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
-            cache
-                .expect_get_signer_approve_and_bal_amts()
-                .returning(|_, _, _| {
-                    Ok(Some((
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(199),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
                         200000000000000000000u128.into(),
-                        100000000000000000u128.into(),
-                    )))
-                });
-            cache.expect_get_synced_block().returning(|_, _| Ok(150));
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::Valid,
-                )))
+                        200000000000000000000u128.into(),
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &registry_with_margins(0, 2),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
-        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
         match response.body() {
-            Body::Text(msg) => assert_eq!(
-                msg,
-                "{\"id\":null,\"cid\":null,\"error\":\"Signer token balance is insufficient\"}"
-            ),
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:bidding-closed\",\"title\":\"Bidding has closed\",\"status\":400,\"detail\":\"Bidding closed at block 198 ahead of the auction ending at block 200 but the latest synced block is 199\",\"code\":\"BIDDING_CLOSED\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"close_block\":198,\"end_block\":200,\"synced_block\":199}"),
             _ => panic!("Malformed response"),
         }
     }
 
     #[tokio::test]
-    async fn request_handler_sig_happy_path() {
-        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+    async fn request_handler_bid_with_two_deadlines() {
+        let mut bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::DeadlineBlock);
+        bid_payload.typed_data.message["deadlineTimestamp"] = "0x5f5e1000".into();
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:malformed-payload\",\"title\":\"Malformed payload\",\"status\":400,\"detail\":\"deadline parsing error: only one of deadlineBlock and deadlineTimestamp can be set\",\"code\":\"MALFORMED_PAYLOAD\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_stores_bid_deadline() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::DeadlineBlock);
         let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
         *r.method_mut() = Method::PUT;
-        let mut mock_cache = Mutex::new(MockCache::new());
+        let mock_cache = Mutex::new(MockCache::new());
 
-        with_lock(&mut mock_cache, |cache| {
+        with_lock(&mock_cache, |cache| {
             cache.expect_is_connected().returning(|| true);
             cache.expect_connect().returning(|| Ok(()));
             cache.expect_ping().returning(|| Ok(()));
-            cache
-                .expect_get_signer_approve_and_bal_amts()
-                .returning(|_, _, _| {
-                    Ok(Some((
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
                         200000000000000000000u128.into(),
                         200000000000000000000u128.into(),
-                    )))
-                });
-            cache.expect_get_synced_block().returning(|_, _| Ok(150));
-            cache.expect_get_auction().returning(|_, _, _| {
-                Ok(Some(dummy_data::new_auction(
-                    dummy_data::AuctionOption::Valid,
-                )))
+                    )),
+                })
             });
         })
         .await;
 
-        let mut mock_db = Mutex::new(MockDatabase::new());
-        with_lock(&mut mock_db, |db| {
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
             db.expect_is_connected().returning(|| true);
             db.expect_connect().returning(|| Ok(()));
             db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
             db.expect_insert_bid()
-                .returning(|_| Ok("0xsomehash".to_string()));
+                .withf(|bid| {
+                    bid.hash() == "14f07e68ae0e22ea229168ba64bfc97ee6dbc2472960124d680a048bde7df404"
+                        && bid.parsed_values.deadline == Some(Deadline::Block(150))
+                })
+                .returning(|bid| {
+                    Ok(dummy_data::new_stored_bid(
+                        &("0x".to_string() + &bid.hash()),
+                    ))
+                });
         })
         .await;
-        let response = put_request_handler(r, &mut mock_cache, &mut mock_db)
-            .await
-            .unwrap();
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_handler_no_signer_approval() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: None,
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:approval-not-found\",\"title\":\"Signer has not approved the settlement contract\",\"status\":403,\"detail\":\"Signer has not approved the settlement contract\",\"code\":\"APPROVAL_NOT_FOUND\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_approval_too_low() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        500000000000000000u128.into(),
+                        100000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
         match response.body() {
-            Body::Text(msg) => assert_eq!(msg, "{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\",\"error\":null}"),
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:multiple-errors\",\"title\":\"Bid failed multiple checks\",\"status\":403,\"detail\":\"Bid costs 1750000000000000000 but the signer approved 500000000000000000; Bid costs 1750000000000000000 but the signer balance is 100000000000000000\",\"code\":\"MULTIPLE_ERRORS\",\"errors\":[{\"available\":\"500000000000000000\",\"code\":\"INSUFFICIENT_APPROVAL\",\"committed\":\"0\",\"detail\":\"Bid costs 1750000000000000000 but the signer approved 500000000000000000\",\"required\":\"1750000000000000000\",\"status\":403,\"title\":\"Signer approval amount is insufficient\",\"type\":\"urn:pikapool:error:insufficient-approval\"},{\"available\":\"100000000000000000\",\"code\":\"INSUFFICIENT_BALANCE\",\"committed\":\"0\",\"detail\":\"Bid costs 1750000000000000000 but the signer balance is 100000000000000000\",\"required\":\"1750000000000000000\",\"status\":403,\"title\":\"Signer token balance is insufficient\",\"type\":\"urn:pikapool:error:insufficient-balance\"}]}"
+            ),
             _ => panic!("Malformed response"),
         }
     }
+
+    #[tokio::test]
+    async fn request_handler_balance_too_low() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        100000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:insufficient-balance\",\"title\":\"Signer token balance is insufficient\",\"status\":403,\"detail\":\"Bid costs 1750000000000000000 but the signer balance is 100000000000000000\",\"code\":\"INSUFFICIENT_BALANCE\",\"available\":\"100000000000000000\",\"committed\":\"0\",\"required\":\"1750000000000000000\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_sig_happy_path() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bid()
+                .withf(|bid| {
+                    bid.hash() == "f22e27606cf0f7aef3ea01563db77f7b5ef16b0528d1441a7e79283ae6d95ef2"
+                })
+                .returning(|_| Ok(dummy_data::new_stored_bid("0xsomehash")));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .withf(|_, id, cid| {
+                    id == "0xsomehash"
+                        && cid == "bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna"
+                })
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["Content-Type"], "application/json");
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_publish_fails() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bid()
+                .returning(|_| Ok(dummy_data::new_stored_bid("0xsomehash")));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .returning(|_, _, _| Err(BidError::Publisher("Queue does not exist".to_string())));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:publisher-error\",\"title\":\"Publisher error\",\"status\":500,\"detail\":\"Queue does not exist\",\"code\":\"PUBLISHER_ERROR\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_resubmitted_bid_was_replaced() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bid().returning(|_| {
+                Ok(StoredBid {
+                    id: "0xsomehash".to_string(),
+                    inserted: false,
+                    status: "replaced".to_string(),
+                })
+            });
+        })
+        .await;
+        // The replaced bid isn't published again
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_publish_bid().never();
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:bid-not-active\",\"title\":\"Bid is no longer active\",\"status\":409,\"detail\":\"Bid has already been replaced\",\"code\":\"BID_NOT_ACTIVE\",\"bid_status\":\"replaced\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_reports_each_bid() {
+        let valid = to_string(&dummy_data::new_bid_payload(
+            dummy_data::BidPayloadOption::Valid,
+        ))
+        .unwrap();
+        let signature_mismatch = to_string(&dummy_data::new_bid_payload(
+            dummy_data::BidPayloadOption::SignatureDoesNotMatchSigner,
+        ))
+        .unwrap();
+        let body = format!(
+            "[{},{},{},{{\"sender\":1}}]",
+            valid, valid, signature_mismatch
+        );
+        let mut r = Request::new(Body::from(body));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            // Both valid bids are on the same auction, so the context is fetched once
+            cache
+                .expect_get_bid_context()
+                .times(1)
+                .returning(|_, _, _, _, _| {
+                    Ok(BidContext {
+                        auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                        synced_block: Some(150),
+                        synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                        signer_approve_and_bal_amts: Some((
+                            200000000000000000000u128.into(),
+                            200000000000000000000u128.into(),
+                        )),
+                    })
+                });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bids()
+                .withf(|bids| bids.len() == 2)
+                .times(1)
+                .returning(|_| {
+                    // The second copy of the bid finds the row the first inserted
+                    let copy = StoredBid {
+                        inserted: false,
+                        ..dummy_data::new_stored_bid("0xsomehash")
+                    };
+                    Ok(vec![Ok(dummy_data::new_stored_bid("0xsomehash")), Ok(copy)])
+                });
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            // Copies of a bid in the batch are published once
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"results\":[{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"},{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"},{\"error\":{\"type\":\"urn:pikapool:error:signature-mismatch\",\"title\":\"Signature does not match signer\",\"status\":400,\"detail\":\"Signature does not match signer\",\"code\":\"SIGNATURE_MISMATCH\"}},{\"error\":{\"type\":\"urn:pikapool:error:malformed-payload\",\"title\":\"Malformed payload\",\"status\":400,\"detail\":\"invalid type: integer `1`, expected a string\",\"code\":\"MALFORMED_PAYLOAD\"}}]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_database_fails() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(format!(
+            "[{}]",
+            to_string(&bid_payload).unwrap()
+        )));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bids()
+                .returning(|_| Err(BidError::Database("connection closed".to_string())));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_publish_bid().never();
+        })
+        .await;
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"results\":[{\"error\":{\"type\":\"urn:pikapool:error:database-error\",\"title\":\"Database error\",\"status\":500,\"detail\":\"connection closed\",\"code\":\"DATABASE_ERROR\"}}]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_empty_batch() {
+        let mut r = Request::new(Body::from("[]"));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(Mutex::new(MockCache::new())),
+            &pool(Mutex::new(MockDatabase::new())),
+            &pool(Mutex::new(MockPublisher::new())),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:invalid-batch-size\",\"title\":\"Invalid batch size\",\"status\":400,\"detail\":\"Batch has 0 bids but must have between 1 and 50\",\"code\":\"INVALID_BATCH_SIZE\",\"max\":50,\"size\":0}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_counts_signer_exposure() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        2000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            // The signer's bid in this auction is replaced, so it is excluded
+            db.expect_get_signer_exposure()
+                .withf(
+                    |signer, settlement_contract, auction_address, auction_name| {
+                        *signer
+                            == "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+                                .parse::<Address>()
+                                .unwrap()
+                            && *settlement_contract
+                                == "0xd2090025857B9C7B24387741f120538E928A3a59"
+                                    .parse::<Address>()
+                                    .unwrap()
+                            && *auction_address
+                                == "0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA"
+                                    .parse::<Address>()
+                                    .unwrap()
+                            && auction_name == "LeafyGreens_Public_Sale"
+                    },
+                )
+                .times(1)
+                .returning(|_, _, _, _| Ok(1000000000000000000u128.into()));
+            db.expect_insert_bid().never();
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:insufficient-balance\",\"title\":\"Signer token balance is insufficient\",\"status\":403,\"detail\":\"Bid costs 1750000000000000000 on top of 1000000000000000000 committed to the signer's other bids but the signer balance is 2000000000000000000\",\"code\":\"INSUFFICIENT_BALANCE\",\"available\":\"2000000000000000000\",\"committed\":\"1000000000000000000\",\"required\":\"2750000000000000000\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_counts_earlier_bids_in_batch() {
+        let body = format!(
+            "[{},{}]",
+            to_string(&dummy_data::new_bid_payload(
+                dummy_data::BidPayloadOption::Valid
+            ))
+            .unwrap(),
+            to_string(&dummy_data::new_bid_payload(
+                dummy_data::BidPayloadOption::OtherAuction
+            ))
+            .unwrap()
+        );
+        let mut r = Request::new(Body::from(body));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache
+                .expect_get_bid_context()
+                .times(2)
+                .returning(|_, _, _, _, _| {
+                    Ok(BidContext {
+                        auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                        synced_block: Some(150),
+                        synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                        signer_approve_and_bal_amts: Some((
+                            200000000000000000000u128.into(),
+                            3000000000000000000u128.into(),
+                        )),
+                    })
+                });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bids()
+                .withf(|bids| bids.len() == 1)
+                .times(1)
+                .returning(|_| Ok(vec![Ok(dummy_data::new_stored_bid("0xsomehash"))]));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"results\":[{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"},{\"error\":{\"type\":\"urn:pikapool:error:insufficient-balance\",\"title\":\"Signer token balance is insufficient\",\"status\":403,\"detail\":\"Bid costs 1750000000000000000 on top of 1750000000000000000 committed to the signer's other bids but the signer balance is 3000000000000000000\",\"code\":\"INSUFFICIENT_BALANCE\",\"available\":\"3000000000000000000\",\"committed\":\"1750000000000000000\",\"required\":\"3500000000000000000\"}}]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_bid_replaced_in_batch() {
+        let body = format!(
+            "[{},{}]",
+            to_string(&dummy_data::new_bid_payload(
+                dummy_data::BidPayloadOption::Valid
+            ))
+            .unwrap(),
+            to_string(&dummy_data::new_bid_payload(
+                dummy_data::BidPayloadOption::Nonce
+            ))
+            .unwrap()
+        );
+        let mut r = Request::new(Body::from(body));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_insert_bids()
+                .withf(|bids| bids.len() == 2)
+                .times(1)
+                .returning(|_| {
+                    // Both bids are on the same auction, so the second
+                    // replaces the first
+                    let replaced = StoredBid {
+                        status: "replaced".to_string(),
+                        ..dummy_data::new_stored_bid("0xfirsthash")
+                    };
+                    Ok(vec![
+                        Ok(replaced),
+                        Ok(dummy_data::new_stored_bid("0xsecondhash")),
+                    ])
+                });
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .withf(|_, bid_id, _| bid_id == "0xsecondhash")
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"results\":[{\"error\":{\"type\":\"urn:pikapool:error:bid-not-active\",\"title\":\"Bid is no longer active\",\"status\":409,\"detail\":\"Bid has already been replaced\",\"code\":\"BID_NOT_ACTIVE\",\"bid_status\":\"replaced\"}},{\"id\":\"0xsecondhash\",\"cid\":\"bafkreibjf4x5ftfee57cl6wufkmbefymjajlbf5dji3yb45ovagzbvdw3a\"}]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_reports_bid_refused_when_stored() {
+        let body = format!(
+            "[{},{}]",
+            to_string(&dummy_data::new_bid_payload(
+                dummy_data::BidPayloadOption::Nonce
+            ))
+            .unwrap(),
+            to_string(&dummy_data::new_bid_payload(
+                dummy_data::BidPayloadOption::OtherAuction
+            ))
+            .unwrap()
+        );
+        let mut r = Request::new(Body::from(body));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_insert_bids()
+                .withf(|bids| bids.len() == 2)
+                .times(1)
+                .returning(|_| {
+                    // A bid with a higher nonce was stored after the first
+                    // bid was validated
+                    Ok(vec![
+                        Err(BidError::StaleNonce {
+                            nonce: 3.into(),
+                            highest: 4.into(),
+                        }),
+                        Ok(dummy_data::new_stored_bid("0xsecondhash")),
+                    ])
+                });
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .withf(|_, bid_id, _| bid_id == "0xsecondhash")
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"results\":[{\"error\":{\"type\":\"urn:pikapool:error:stale-nonce\",\"title\":\"Nonce is not above the signer's highest nonce\",\"status\":400,\"detail\":\"Nonce 3 must be above the signer's highest nonce 4 in this auction\",\"code\":\"STALE_NONCE\",\"highest_nonce\":\"4\",\"nonce\":\"3\"}},{\"id\":\"0xsecondhash\",\"cid\":\"bafkreibjf4x5ftfee57cl6wufkmbefymjajlbf5dji3yb45ovagzbvdw3a\"}]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_stale_nonce() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Nonce);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(Some(3.into())));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:stale-nonce\",\"title\":\"Nonce is not above the signer's highest nonce\",\"status\":400,\"detail\":\"Nonce 3 must be above the signer's highest nonce 3 in this auction\",\"code\":\"STALE_NONCE\",\"highest_nonce\":\"3\",\"nonce\":\"3\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_bid_without_nonce_after_nonce() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            // The signer has a Bid with a nonce in the auction
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(Some(3.into())));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:stale-nonce\",\"title\":\"Nonce is not above the signer's highest nonce\",\"status\":400,\"detail\":\"Nonce 0 must be above the signer's highest nonce 3 in this auction\",\"code\":\"STALE_NONCE\",\"highest_nonce\":\"3\",\"nonce\":\"0\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_stores_bid_nonce() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Nonce);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            // The Bid itself is excluded so it can be resubmitted
+            db.expect_get_highest_nonce()
+                .withf(|_, _, auction_name, excluded_bid_id| {
+                    auction_name == "LeafyGreens_Public_Sale"
+                        && excluded_bid_id
+                            == "4634a974c8879cbf288c5814ddb0bdcceece4d56cbe2d9682b2e3bdb110f5824"
+                })
+                .returning(|_, _, _, _| Ok(Some(2.into())));
+            db.expect_insert_bid()
+                .withf(|bid| bid.parsed_values.nonce == Some(3.into()))
+                .returning(|bid| {
+                    Ok(dummy_data::new_stored_bid(
+                        &("0x".to_string() + &bid.hash()),
+                    ))
+                });
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn batch_put_request_handler_rejects_nonce_repeated_in_batch() {
+        let bid = to_string(&dummy_data::new_bid_payload(
+            dummy_data::BidPayloadOption::Nonce,
+        ))
+        .unwrap();
+        let mut r = Request::new(Body::from(format!("[{},{}]", bid, bid)));
+        *r.method_mut() = Method::PUT;
+        *r.uri_mut() = "/v0/bids/batch".parse().unwrap();
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_insert_bids()
+                .withf(|bids| bids.len() == 1)
+                .times(1)
+                .returning(|_| Ok(vec![Ok(dummy_data::new_stored_bid("0xsomehash"))]));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = batch_put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"results\":[{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"},{\"error\":{\"type\":\"urn:pikapool:error:stale-nonce\",\"title\":\"Nonce is not above the signer's highest nonce\",\"status\":400,\"detail\":\"Nonce 3 must be above the signer's highest nonce 3 in this auction\",\"code\":\"STALE_NONCE\",\"highest_nonce\":\"3\",\"nonce\":\"3\"}}]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_contract_wallet_signature() {
+        // Signed by another key, which the sender's contract wallet accepts
+        let bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::SignatureDoesNotMatchSigner);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bid()
+                .times(1)
+                .returning(|_| Ok(dummy_data::new_stored_bid("0xsomehash")));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let mock_verifier = Mutex::new(MockVerifier::new());
+        with_lock(&mock_verifier, |verifier| {
+            verifier.expect_is_connected().returning(|| true);
+            verifier.expect_connect().returning(|| Ok(()));
+            verifier.expect_ping().returning(|| Ok(()));
+            verifier
+                .expect_is_contract_wallet()
+                .withf(|chain_id, wallet| {
+                    chain_id == "1"
+                        && *wallet
+                            == "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+                                .parse::<Address>()
+                                .unwrap()
+                })
+                .times(1)
+                .returning(|_, _| Ok(true));
+            verifier
+                .expect_is_valid_signature()
+                .withf(|_, _, _, signature| signature.len() == 65)
+                .times(1)
+                .returning(|_, _, _, _| Ok(true));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &pool(mock_verifier),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn request_handler_stores_empty_contract_wallet_signature() {
+        // Contract wallets that approve message hashes on-chain sign with nothing
+        let mut bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::SignatureDoesNotMatchSigner);
+        bid_payload.signature = "".to_string();
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bid()
+                .withf(|bid| bid.payload.signature == "0x")
+                .times(1)
+                .returning(|_| Ok(dummy_data::new_stored_bid("0xsomehash")));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher
+                .expect_publish_bid()
+                .times(1)
+                .returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let mock_verifier = Mutex::new(MockVerifier::new());
+        with_lock(&mock_verifier, |verifier| {
+            verifier.expect_is_connected().returning(|| true);
+            verifier.expect_connect().returning(|| Ok(()));
+            verifier.expect_ping().returning(|| Ok(()));
+            verifier
+                .expect_is_contract_wallet()
+                .returning(|_, _| Ok(true));
+            verifier
+                .expect_is_valid_signature()
+                .withf(|_, _, _, signature| signature.is_empty())
+                .times(1)
+                .returning(|_, _, _, _| Ok(true));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &pool(mock_verifier),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_handler_contract_wallet_rejects_signature() {
+        let bid_payload =
+            dummy_data::new_bid_payload(dummy_data::BidPayloadOption::SignatureDoesNotMatchSigner);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let mock_verifier = Mutex::new(MockVerifier::new());
+        with_lock(&mock_verifier, |verifier| {
+            verifier.expect_is_connected().returning(|| true);
+            verifier.expect_connect().returning(|| Ok(()));
+            verifier.expect_ping().returning(|| Ok(()));
+            verifier
+                .expect_is_contract_wallet()
+                .returning(|_, _| Ok(true));
+            verifier
+                .expect_is_valid_signature()
+                .returning(|_, _, _, _| Ok(false));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &pool(mock_verifier),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:signature-mismatch\",\"title\":\"Signature does not match signer\",\"status\":400,\"detail\":\"Signature does not match signer\",\"code\":\"SIGNATURE_MISMATCH\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    const VALID_BID_HASH: &str = "f22e27606cf0f7aef3ea01563db77f7b5ef16b0528d1441a7e79283ae6d95ef2";
+    const VALID_BID_SIGNER: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+    const VALID_BID_SIGNATURE: &str = "0x881d5c3742e33a662fb2de9f906ac084badb4faf630e79acea39a9f3951e042c5769af5838bf3e5fe80705a3ae01b900a6ef119267322c11b8ca06e5de57f4f01c";
+    // EIP-2098 form of VALID_BID_SIGNATURE
+    const VALID_BID_COMPACT_SIGNATURE: &str = "0x881d5c3742e33a662fb2de9f906ac084badb4faf630e79acea39a9f3951e042cd769af5838bf3e5fe80705a3ae01b900a6ef119267322c11b8ca06e5de57f4f0";
+
+    fn valid_bid_hash() -> [u8; 32] {
+        hex::decode(VALID_BID_HASH).unwrap().try_into().unwrap()
+    }
+
+    #[test]
+    fn verify_signature_accepts_compact_signature() {
+        let signer: Address = VALID_BID_SIGNER.parse().unwrap();
+        let signature = verify_signature(
+            signer,
+            valid_bid_hash(),
+            &VALID_BID_COMPACT_SIGNATURE.to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            format!("0x{}", signature),
+            VALID_BID_SIGNATURE,
+            "compact signature expands to the 65-byte signature"
+        );
+    }
+
+    #[test]
+    fn verify_signature_rejects_high_s_signature() {
+        let signer: Address = VALID_BID_SIGNER.parse().unwrap();
+        // s' = n - s with the recovery id flipped recovers the same signer
+        let high_s_signature = "0x881d5c3742e33a662fb2de9f906ac084badb4faf630e79acea39a9f3951e042ca89650a7c740c1a017f8fa5c51fe46fe13bfcb544816742a070857a6f1de4c511b";
+
+        assert_eq!(
+            verify_signature(signer, valid_bid_hash(), &high_s_signature.to_string()),
+            Err(BidError::NonCanonicalSignature)
+        );
+        assert!(
+            verify_signature(signer, valid_bid_hash(), &VALID_BID_SIGNATURE.to_string()).is_ok()
+        );
+    }
+
+    #[test]
+    fn verify_signature_rejects_compact_signature_with_wrong_parity() {
+        let signer: Address = VALID_BID_SIGNER.parse().unwrap();
+        let mut signature = hex::decode(&VALID_BID_COMPACT_SIGNATURE[2..]).unwrap();
+        signature[32] ^= 0x80;
+
+        assert_eq!(
+            verify_signature(
+                signer,
+                valid_bid_hash(),
+                &format!("0x{}", hex::encode(signature))
+            ),
+            Err(BidError::SignatureMismatch)
+        );
+    }
+
+    #[tokio::test]
+    async fn request_handler_stores_expanded_compact_signature() {
+        let mut bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        bid_payload.signature = VALID_BID_COMPACT_SIGNATURE.to_string();
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(150),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_insert_bid()
+                .withf(|bid| {
+                    bid.payload.signature == VALID_BID_SIGNATURE && bid.hash() == VALID_BID_HASH
+                })
+                .times(1)
+                .returning(|_| Ok(dummy_data::new_stored_bid("0xsomehash")));
+        })
+        .await;
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        with_lock(&mock_publisher, |publisher| {
+            publisher.expect_is_connected().returning(|| true);
+            publisher.expect_connect().returning(|| Ok(()));
+            publisher.expect_ping().returning(|| Ok(()));
+            publisher.expect_publish_bid().returning(|_, _, _| Ok(()));
+        })
+        .await;
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn request_handler_reads_without_chain_registry() {
+        // CHAIN_REGISTRY isn't set for tests
+        let mut r = Request::default();
+        *r.method_mut() = Method::OPTIONS;
+        let response = request_handler(r).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut r = Request::default();
+        *r.method_mut() = Method::GET;
+        *r.uri_mut() = "/v0/bids/0x1234".parse().unwrap();
+        let response = request_handler(r).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn get_request_handler_invalid_bid_id() {
+        let mut r = Request::default();
+        *r.method_mut() = Method::GET;
+        *r.uri_mut() = "/v0/bids/0x1234".parse().unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = get_request_handler(r, &pool(mock_db)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:invalid-bid-id\",\"title\":\"Invalid bid id\",\"status\":400,\"detail\":\"Invalid bid id\",\"code\":\"INVALID_BID_ID\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_request_handler_bid_not_found() {
+        let mut r = Request::default();
+        *r.method_mut() = Method::GET;
+        *r.uri_mut() = format!("/v0/bids/{}", dummy_data::BID_ID).parse().unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_get_bid().returning(|_| Ok(None));
+        })
+        .await;
+        let response = get_request_handler(r, &pool(mock_db)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:bid-not-found\",\"title\":\"Bid does not exist\",\"status\":404,\"detail\":\"Bid does not exist\",\"code\":\"BID_NOT_FOUND\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_request_handler_replaced_bid() {
+        let mut r = Request::default();
+        *r.method_mut() = Method::GET;
+        *r.uri_mut() = format!("/v0/bids/{}", dummy_data::BID_ID).parse().unwrap();
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_get_bid()
+                .with(eq(dummy_data::BID_ID))
+                .returning(|_| Ok(Some(dummy_data::new_bid_record("replaced"))));
+        })
+        .await;
+        let response = get_request_handler(r, &pool(mock_db)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                &to_string(&dummy_data::new_bid_record("replaced")).unwrap()
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_request_handler_no_signer() {
+        let r = new_list_request(&[]);
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = get_request_handler(r, &pool(mock_db)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:invalid-query\",\"title\":\"Invalid query\",\"status\":400,\"detail\":\"signer query parameter is required\",\"code\":\"INVALID_QUERY\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_request_handler_invalid_cursor() {
+        let r = new_list_request(&[
+            ("signer", "0x36bCaEE2F1f6C185f91608C7802f6Fc4E8bD9f1d"),
+            ("cursor", "yesterday"),
+        ]);
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = get_request_handler(r, &pool(mock_db)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:invalid-query\",\"title\":\"Invalid query\",\"status\":400,\"detail\":\"Invalid cursor\",\"code\":\"INVALID_QUERY\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_request_handler_paginates() {
+        let r = new_list_request(&[
+            ("signer", "0x36bCaEE2F1f6C185f91608C7802f6Fc4E8bD9f1d"),
+            ("status", "submitted"),
+            ("limit", "1"),
+        ]);
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_list_bids()
+                .withf(|query| {
+                    query.limit == 2
+                        && query.status == Some("submitted".to_string())
+                        && query.auction_address.is_none()
+                        && query.cursor.is_none()
+                })
+                .returning(|_| {
+                    let mut older_bid = dummy_data::new_bid_record("submitted");
+                    older_bid.id = dummy_data::REPLACEMENT_BID_ID.to_string();
+                    Ok(vec![dummy_data::new_bid_record("submitted"), older_bid])
+                });
+        })
+        .await;
+        let response = get_request_handler(r, &pool(mock_db)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                &format!(
+                    "{{\"bids\":[{}],\"next_cursor\":\"1673352000000000_{}\"}}",
+                    to_string(&dummy_data::new_bid_record("submitted")).unwrap(),
+                    dummy_data::BID_ID
+                )
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_request_handler_bidder_doesnt_match_signer() {
+        let cancel_payload = dummy_data::new_cancel_payload(
+            dummy_data::CancelPayloadOption::BidderDoesNotMatchSigner,
+        );
+        let mut r = Request::new(Body::from(to_string(&cancel_payload).unwrap()));
+        *r.method_mut() = Method::DELETE;
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = delete_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:bidder-mismatch\",\"title\":\"Bidder does not match signer\",\"status\":400,\"detail\":\"Bidder does not match signer\",\"code\":\"BIDDER_MISMATCH\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_request_handler_sig_doesnt_match_signer() {
+        let cancel_payload = dummy_data::new_cancel_payload(
+            dummy_data::CancelPayloadOption::SignatureDoesNotMatchSigner,
+        );
+        let mut r = Request::new(Body::from(to_string(&cancel_payload).unwrap()));
+        *r.method_mut() = Method::DELETE;
+        let mock_cache = Mutex::new(MockCache::new());
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = delete_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:signature-mismatch\",\"title\":\"Signature does not match signer\",\"status\":400,\"detail\":\"Signature does not match signer\",\"code\":\"SIGNATURE_MISMATCH\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_request_handler_auction_has_ended() {
+        let cancel_payload = dummy_data::new_cancel_payload(dummy_data::CancelPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&cancel_payload).unwrap()));
+        *r.method_mut() = Method::DELETE;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_synced_block().returning(|_, _| Ok(201));
+            cache
+                .expect_get_synced_timestamp()
+                .returning(|_, _| Ok(Some(dummy_data::new_synced_timestamp())));
+            cache.expect_get_auction().returning(|_, _, _| {
+                Ok(Some(dummy_data::new_auction(
+                    dummy_data::AuctionOption::Valid,
+                )))
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = delete_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:auction-ended\",\"title\":\"Auction has ended\",\"status\":400,\"detail\":\"Auction ended at block 200 but the latest synced block is 201\",\"code\":\"AUCTION_ENDED\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"end_block\":200,\"synced_block\":201}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_request_handler_chain_state_stale() {
+        let cancel_payload = dummy_data::new_cancel_payload(dummy_data::CancelPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&cancel_payload).unwrap()));
+        *r.method_mut() = Method::DELETE;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_synced_block().returning(|_, _| Ok(150));
+            cache
+                .expect_get_synced_timestamp()
+                .returning(|_, _| Ok(Some(dummy_data::new_synced_timestamp() - 61)));
+            // Not synced yet rather than missing
+            cache.expect_get_auction().returning(|_, _, _| Ok(None));
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = delete_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let problem: Value = match response.body() {
+            Body::Text(msg) => from_str(msg).unwrap(),
+            _ => panic!("Malformed response"),
+        };
+        assert_eq!(problem["code"], "CHAIN_STATE_STALE");
+    }
+
+    #[tokio::test]
+    async fn delete_request_handler_no_active_bid() {
+        let cancel_payload = dummy_data::new_cancel_payload(dummy_data::CancelPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&cancel_payload).unwrap()));
+        *r.method_mut() = Method::DELETE;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_synced_block().returning(|_, _| Ok(150));
+            cache
+                .expect_get_synced_timestamp()
+                .returning(|_, _| Ok(Some(dummy_data::new_synced_timestamp())));
+            cache.expect_get_auction().returning(|_, _, _| {
+                Ok(Some(dummy_data::new_auction(
+                    dummy_data::AuctionOption::Valid,
+                )))
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_cancel_bid().returning(|_, _, _, _| Ok(None));
+        })
+        .await;
+        let response = delete_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"type\":\"urn:pikapool:error:no-active-bid\",\"title\":\"Signer has no active bid with the specified id\",\"status\":404,\"detail\":\"Signer has no active bid with the specified id\",\"code\":\"NO_ACTIVE_BID\"}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_request_handler_happy_path() {
+        let cancel_payload = dummy_data::new_cancel_payload(dummy_data::CancelPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&cancel_payload).unwrap()));
+        *r.method_mut() = Method::DELETE;
+        let mock_cache = Mutex::new(MockCache::new());
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_synced_block().returning(|_, _| Ok(150));
+            cache
+                .expect_get_synced_timestamp()
+                .returning(|_, _| Ok(Some(dummy_data::new_synced_timestamp())));
+            cache.expect_get_auction().returning(|_, _, _| {
+                Ok(Some(dummy_data::new_auction(
+                    dummy_data::AuctionOption::Valid,
+                )))
+            });
+        })
+        .await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+            db.expect_cancel_bid()
+                .withf(|bid_id, auction_address, auction_name, signer| {
+                    bid_id == dummy_data::BID_ID
+                        && *auction_address
+                            == "0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA"
+                                .parse::<Address>()
+                                .unwrap()
+                        && auction_name == "LeafyGreens_Public_Sale"
+                        && *signer
+                            == "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23"
+                                .parse::<Address>()
+                                .unwrap()
+                })
+                .returning(|_, _, _, _| Ok(Some("0xsomehash".to_string())));
+        })
+        .await;
+        let response = delete_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"id\":\"0xsomehash\",\"cid\":\"bafkreicmgtpekgtrzmcahkzcpcrkalfxagfw2crbt4kqts7fxsrexjxzna\"}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_request_handler_valid_bid() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::POST;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(100),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let response = validate_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"valid\":true,\"id\":\"0xf22e27606cf0f7aef3ea01563db77f7b5ef16b0528d1441a7e79283ae6d95ef2\",\"checks\":[{\"check\":\"schema\",\"status\":\"passed\"},{\"check\":\"chain\",\"status\":\"passed\"},{\"check\":\"signer\",\"status\":\"passed\"},{\"check\":\"auction_address\",\"status\":\"passed\"},{\"check\":\"signature\",\"status\":\"passed\"},{\"check\":\"bidder\",\"status\":\"passed\"},{\"check\":\"auction\",\"status\":\"passed\"},{\"check\":\"base_price\",\"status\":\"passed\"},{\"check\":\"auction_window\",\"status\":\"passed\"},{\"check\":\"deadline\",\"status\":\"passed\"},{\"check\":\"nonce\",\"status\":\"passed\"},{\"check\":\"approval\",\"status\":\"passed\"},{\"check\":\"balance\",\"status\":\"passed\"}]}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_request_handler_reports_failed_checks() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::POST;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache.expect_get_bid_context().returning(|_, _, _, _, _| {
+                Ok(BidContext {
+                    auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                    synced_block: Some(99),
+                    synced_timestamp: Some(dummy_data::new_synced_timestamp()),
+                    signer_approve_and_bal_amts: Some((
+                        200000000000000000000u128.into(),
+                        200000000000000000000u128.into(),
+                    )),
+                })
+            });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        with_lock(&mock_db, |db| {
+            db.expect_is_connected().returning(|| true);
+            db.expect_connect().returning(|| Ok(()));
+            db.expect_ping().returning(|| Ok(()));
+            db.expect_get_highest_nonce()
+                .returning(|_, _, _, _| Ok(None));
+            db.expect_get_signer_exposure()
+                .returning(|_, _, _, _| Ok(U256::zero()));
+        })
+        .await;
+        let response = validate_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(
+                msg,
+                "{\"valid\":false,\"id\":null,\"checks\":[{\"check\":\"schema\",\"status\":\"passed\"},{\"check\":\"chain\",\"status\":\"passed\"},{\"check\":\"signer\",\"status\":\"passed\"},{\"check\":\"auction_address\",\"status\":\"passed\"},{\"check\":\"signature\",\"status\":\"passed\"},{\"check\":\"bidder\",\"status\":\"passed\"},{\"check\":\"auction\",\"status\":\"passed\"},{\"check\":\"base_price\",\"status\":\"passed\"},{\"check\":\"auction_window\",\"status\":\"failed\",\"problem\":{\"type\":\"urn:pikapool:error:auction-not-started\",\"title\":\"Auction has not started\",\"status\":400,\"detail\":\"Auction starts at block 100 but the latest synced block is 99\",\"code\":\"AUCTION_NOT_STARTED\",\"auction_address\":\"0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea\",\"start_block\":100,\"synced_block\":99}},{\"check\":\"deadline\",\"status\":\"passed\"},{\"check\":\"nonce\",\"status\":\"passed\"},{\"check\":\"approval\",\"status\":\"passed\"},{\"check\":\"balance\",\"status\":\"passed\"}]}"
+            ),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_request_handler_chain_state_stale() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::POST;
+        let mock_cache = Mutex::new(MockCache::new());
+
+        with_lock(&mock_cache, |cache| {
+            cache.expect_is_connected().returning(|| true);
+            cache.expect_connect().returning(|| Ok(()));
+            cache.expect_ping().returning(|| Ok(()));
+            cache
+                .expect_get_bid_context()
+                .returning(move |_, _, _, _, _| {
+                    Ok(BidContext {
+                        auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                        synced_block: Some(150),
+                        synced_timestamp: Some(dummy_data::new_synced_timestamp() - 61),
+                        signer_approve_and_bal_amts: Some((
+                            200000000000000000000u128.into(),
+                            200000000000000000000u128.into(),
+                        )),
+                    })
+                });
+        })
+        .await;
+
+        let mock_db = Mutex::new(MockDatabase::new());
+        let response = validate_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(mock_cache),
+            &pool(mock_db),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        // The checks can't be run against stale chain state
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let problem: Value = match response.body() {
+            Body::Text(msg) => from_str(msg).unwrap(),
+            _ => panic!("Malformed response"),
+        };
+        assert_eq!(problem["code"], "CHAIN_STATE_STALE");
+    }
+
+    #[test]
+    fn chain_registry_checks_sync_age() {
+        let registry = dummy_data::new_chain_registry();
+        let received_time = Utc.timestamp_opt(1700000060, 0).unwrap();
+
+        assert_eq!(
+            registry.check_sync_age("1", Some(1700000000), received_time),
+            Ok(())
+        );
+        assert_eq!(
+            registry.check_sync_age("1", Some(1699999999), received_time),
+            Err(BidError::ChainStateStale {
+                synced_timestamp: Some(1699999999),
+                received_timestamp: 1700000060,
+                max_sync_age: 60,
+            })
+        );
+        // A sync time ahead of the received time is not stale
+        assert_eq!(
+            registry.check_sync_age("1", Some(1700000061), received_time),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn chain_registry_prefers_auction_bidding_margins() {
+        let registry = registry_with_margins(5, 2);
+        let mut auction = dummy_data::new_auction(dummy_data::AuctionOption::Valid);
+
+        assert_eq!(registry.bidding_margins("1", &auction), (5, 2));
+        auction.end_margin_blocks = Some(0);
+        assert_eq!(registry.bidding_margins("1", &auction), (5, 0));
+    }
+
+    #[test]
+    fn key_schema_uses_full_lowercase_addresses() {
+        let key_schema = KeySchema {
+            prefix: String::new(),
+            read_legacy: false,
+        };
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+        let signer = Address::from_str(VALID_BID_SIGNER).unwrap();
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+
+        assert_eq!(
+            key_schema.synced_block_keys("1", &settlement_contract),
+            vec!["v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:syncedBlock"]
+        );
+        assert_eq!(
+            key_schema.signer_details_keys("1", &settlement_contract, &signer),
+            vec!["v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:signer:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"]
+        );
+        assert_eq!(
+            key_schema.auction_keys("1", &auction_contract, "LeafyGreens_Public_Sale"),
+            vec!["v2:1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale"]
+        );
+        assert_eq!(
+            key_schema.contract_wallet_keys("1", &signer),
+            vec!["v2:1:contractWallet:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"]
+        );
+    }
+
+    #[test]
+    fn key_schema_separates_settlement_contracts_sharing_a_prefix() {
+        let key_schema = KeySchema {
+            prefix: String::new(),
+            read_legacy: false,
+        };
+        let signer = Address::from_str(VALID_BID_SIGNER).unwrap();
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+        let other_settlement_contract =
+            Address::from_str("0xd2091111111111111111111111111111111111e1").unwrap();
+
+        assert_ne!(
+            key_schema.synced_block_keys("1", &settlement_contract),
+            key_schema.synced_block_keys("1", &other_settlement_contract)
+        );
+        assert_ne!(
+            key_schema.signer_details_keys("1", &settlement_contract, &signer),
+            key_schema.signer_details_keys("1", &other_settlement_contract, &signer)
+        );
+    }
+
+    #[test]
+    fn key_schema_prefixes_keys() {
+        let key_schema = KeySchema {
+            prefix: "pikapool".to_string(),
+            read_legacy: false,
+        };
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+
+        assert_eq!(
+            key_schema.synced_block_keys("5", &settlement_contract),
+            vec!["pikapool:v2:5:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:syncedBlock"]
+        );
+    }
+
+    #[test]
+    fn key_schema_compat_falls_back_to_legacy_keys() {
+        let key_schema = KeySchema {
+            prefix: "pikapool".to_string(),
+            read_legacy: true,
+        };
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+        let signer = Address::from_str(VALID_BID_SIGNER).unwrap();
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+
+        assert_eq!(
+            key_schema.synced_block_keys("1", &settlement_contract),
+            vec![
+                "pikapool:v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:syncedBlock",
+                "1:d209:syncedBlock"
+            ]
+        );
+        assert_eq!(
+            key_schema.signer_details_keys("1", &settlement_contract, &signer),
+            vec![
+                "pikapool:v2:1:settlement:0xd2090025857b9c7b24387741f120538e928a3a59:signer:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23",
+                "1:d209:2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+            ]
+        );
+        assert_eq!(
+            key_schema.auction_keys("1", &auction_contract, "LeafyGreens_Public_Sale"),
+            vec![
+                "pikapool:v2:1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale",
+                "1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale"
+            ]
+        );
+        assert_eq!(
+            key_schema.contract_wallet_keys("1", &signer),
+            vec![
+                "pikapool:v2:1:contractWallet:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23",
+                "1:contractWallet:0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_cache_gets_bid_context() {
+        let mut cache = fake_redis_without("").connect(v2_keys()).await;
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+        let signer = Address::from_str(VALID_BID_SIGNER).unwrap();
+
+        let bid_context = cache
+            .get_bid_context(
+                "1",
+                &auction_contract,
+                "LeafyGreens_Public_Sale",
+                &settlement_contract,
+                &signer,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            bid_context,
+            BidContext {
+                auction: Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid)),
+                synced_block: Some(150),
+                synced_timestamp: Some(1700000000),
+                signer_approve_and_bal_amts: Some((
+                    U256::MAX,
+                    U256::from_dec_str("200000000000000000000").unwrap()
+                )),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_cache_compat_reads_legacy_keys() {
+        let legacy = FakeRedis::default()
+            .with_hash(
+                "1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale",
+                &[
+                    ("startBlock", "100"),
+                    ("endBlock", "200"),
+                    (
+                        "settlementContract",
+                        "0xd2090025857B9C7B24387741f120538E928A3a59",
+                    ),
+                    ("basePrice", "250000000000000000"),
+                ],
+            )
+            .with_string("1:d209:syncedBlock", "150");
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+
+        let mut cache = legacy
+            .clone()
+            .connect(KeySchema {
+                prefix: String::new(),
+                read_legacy: true,
+            })
+            .await;
+        assert_eq!(
+            cache
+                .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+                .await
+                .unwrap(),
+            Some(dummy_data::new_auction(dummy_data::AuctionOption::Valid))
+        );
+        assert_eq!(
+            cache
+                .get_synced_block("1", &settlement_contract)
+                .await
+                .unwrap(),
+            150
+        );
+
+        let mut cache = legacy.connect(v2_keys()).await;
+        assert_eq!(
+            cache
+                .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_cache_reads_auction_bidding_margins() {
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+        let auction_fields = [
+            ("startBlock", "100"),
+            ("endBlock", "200"),
+            (
+                "settlementContract",
+                "0xd2090025857B9C7B24387741f120538E928A3a59",
+            ),
+            ("basePrice", "250000000000000000"),
+        ];
+        let mut cache = FakeRedis::default()
+            .with_hash(
+                AUCTION_KEY,
+                &[auction_fields.as_slice(), &[("endMarginBlocks", "3")]].concat(),
+            )
+            .connect(v2_keys())
+            .await;
+        let mut expected = dummy_data::new_auction(dummy_data::AuctionOption::Valid);
+        expected.end_margin_blocks = Some(3);
+
+        let auction = cache
+            .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+            .await;
+
+        assert_eq!(auction, Ok(Some(expected)));
+
+        let mut cache = FakeRedis::default()
+            .with_hash(
+                AUCTION_KEY,
+                &[auction_fields.as_slice(), &[("startGraceBlocks", "soon")]].concat(),
+            )
+            .connect(v2_keys())
+            .await;
+
+        let auction = cache
+            .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+            .await;
+
+        assert_eq!(
+            auction,
+            Err(BidError::MalformedCacheValue {
+                key: AUCTION_KEY.to_string(),
+                field: Some("startGraceBlocks".to_string()),
+                value: "soon".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn redis_cache_returns_none_for_missing_auction() {
+        let mut cache = FakeRedis::default().connect(v2_keys()).await;
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+
+        let auction = cache
+            .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+            .await
+            .unwrap();
+
+        assert_eq!(auction, None);
+    }
+
+    #[tokio::test]
+    async fn redis_cache_reports_corrupt_auction() {
+        let mut cache = fake_redis_without("basePrice").connect(v2_keys()).await;
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+        let signer = Address::from_str(VALID_BID_SIGNER).unwrap();
+        let corrupt_cache_entry = BidError::CorruptCacheEntry {
+            key: AUCTION_KEY.to_string(),
+            missing_fields: vec!["basePrice".to_string()],
+        };
+
+        let auction = cache
+            .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+            .await;
+        let bid_context = cache
+            .get_bid_context(
+                "1",
+                &auction_contract,
+                "LeafyGreens_Public_Sale",
+                &settlement_contract,
+                &signer,
+            )
+            .await;
+
+        assert_eq!(auction, Err(corrupt_cache_entry.clone()));
+        assert_eq!(bid_context, Err(corrupt_cache_entry));
+    }
+
+    #[tokio::test]
+    async fn redis_cache_reports_malformed_numeric_fields() {
+        let auction_contract =
+            Address::from_str("0xFeebabE6b0418eC13b30aAdF129F5DcDd4f70CeA").unwrap();
+        let settlement_contract =
+            Address::from_str("0xd2090025857B9C7B24387741f120538E928A3a59").unwrap();
+        let signer = Address::from_str(VALID_BID_SIGNER).unwrap();
+        let mut cache = fake_redis_without("")
+            .with_hash(
+                AUCTION_KEY,
+                &[
+                    ("startBlock", "one hundred"),
+                    ("endBlock", "200"),
+                    (
+                        "settlementContract",
+                        "0xd2090025857B9C7B24387741f120538E928A3a59",
+                    ),
+                    ("basePrice", "250000000000000000"),
+                ],
+            )
+            .with_hash(
+                SIGNER_DETAILS_KEY,
+                &[("approveValue", "MAX_INT256"), ("balanceValue", "2e20")],
+            )
+            .with_string(SYNCED_BLOCK_KEY, "-1")
+            .connect(v2_keys())
+            .await;
+
+        let auction = cache
+            .get_auction("1", &auction_contract, "LeafyGreens_Public_Sale")
+            .await;
+        let amts = cache
+            .get_signer_approve_and_bal_amts("1", &settlement_contract, &signer)
+            .await;
+        let synced_block = cache.get_synced_block("1", &settlement_contract).await;
+
+        assert_eq!(
+            auction,
+            Err(BidError::MalformedCacheValue {
+                key: AUCTION_KEY.to_string(),
+                field: Some("startBlock".to_string()),
+                value: "one hundred".to_string(),
+            })
+        );
+        assert_eq!(
+            amts,
+            Err(BidError::MalformedCacheValue {
+                key: SIGNER_DETAILS_KEY.to_string(),
+                field: Some("balanceValue".to_string()),
+                value: "2e20".to_string(),
+            })
+        );
+        assert_eq!(
+            synced_block,
+            Err(BidError::MalformedCacheValue {
+                key: SYNCED_BLOCK_KEY.to_string(),
+                field: None,
+                value: "-1".to_string(),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn request_handler_corrupt_cache_entry() {
+        let bid_payload = dummy_data::new_bid_payload(dummy_data::BidPayloadOption::Valid);
+        let mut r = Request::new(Body::from(to_string(&bid_payload).unwrap()));
+        *r.method_mut() = Method::PUT;
+        let cache = fake_redis_without("basePrice").connect(v2_keys()).await;
+        let mock_db = Mutex::new(MockDatabase::new());
+        let mock_publisher = Mutex::new(MockPublisher::new());
+        let response = put_request_handler(
+            r,
+            &dummy_data::new_chain_registry(),
+            &pool(Mutex::new(cache)),
+            &pool(mock_db),
+            &pool(mock_publisher),
+            &no_contract_wallets(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        match response.body() {
+            Body::Text(msg) => assert_eq!(msg, "{\"type\":\"urn:pikapool:error:corrupt-cache-entry\",\"title\":\"Cache entry is missing fields\",\"status\":500,\"detail\":\"Cache entry v2:1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale is missing basePrice\",\"code\":\"CORRUPT_CACHE_ENTRY\",\"key\":\"v2:1:auction:0xfeebabe6b0418ec13b30aadf129f5dcdd4f70cea:LeafyGreens_Public_Sale\",\"missing_fields\":[\"basePrice\"]}"),
+            _ => panic!("Malformed response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_pool_waits_for_idle_connection() {
+        let mut connection = MockConnectable::new();
+        connection.expect_is_connected().returning(|| true);
+        connection.expect_ping().times(2).returning(|| Ok(()));
+        let pool = ConnectionPool::new(vec![connection]);
+
+        let first = pool.get().await.unwrap();
+        let (second, _) = tokio::join!(pool.get(), async move {
+            tokio::task::yield_now().await;
+            drop(first);
+        });
+
+        assert!(second.is_ok());
+    }
+
+    #[tokio::test]
+    async fn provider_verifier_without_provider_for_chain() {
+        let mut verifier = ProviderVerifier {
+            providers: HashMap::new(),
+        };
+        let wallet: Address = "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+            .parse()
+            .unwrap();
+
+        // The signer is then checked as an ECDSA signer only
+        let is_contract_wallet = verifier.is_contract_wallet("5", &wallet).await;
+        assert_eq!(is_contract_wallet, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn provider_verifier_unreachable_node_only_affects_its_chain() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let mut verifier = ProviderVerifier {
+            providers: HashMap::from([
+                ("1".to_string(), Provider::<Http>::try_from(url).unwrap()),
+                (
+                    "5".to_string(),
+                    fake_rpc(r#"{"jsonrpc":"2.0","id":1,"result":"0x"}"#).await,
+                ),
+            ]),
+        };
+        let wallet: Address = "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+            .parse()
+            .unwrap();
+
+        // Checking out the verifier doesn't call any node
+        assert_eq!(verifier.ping().await, Ok(()));
+        let is_contract_wallet = verifier.is_contract_wallet("5", &wallet).await;
+        assert_eq!(is_contract_wallet, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn provider_verifier_reverted_call_rejects_signature() {
+        let provider = fake_rpc(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":3,"message":"execution reverted"}}"#,
+        )
+        .await;
+        let mut verifier = ProviderVerifier {
+            providers: HashMap::from([("1".to_string(), provider)]),
+        };
+        let wallet: Address = "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+            .parse()
+            .unwrap();
+
+        let is_valid = verifier
+            .is_valid_signature("1", &wallet, [0; 32], &[])
+            .await;
+        assert_eq!(is_valid, Ok(false));
+    }
+
+    #[tokio::test]
+    async fn provider_verifier_reports_unreachable_node() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let mut verifier = ProviderVerifier {
+            providers: HashMap::from([("1".to_string(), Provider::<Http>::try_from(url).unwrap())]),
+        };
+        let wallet: Address = "0xAB2a3d9F938E13CD947Ec05AbC7FE734Df8DD820"
+            .parse()
+            .unwrap();
+
+        let is_valid = verifier
+            .is_valid_signature("1", &wallet, [0; 32], &[])
+            .await;
+        assert!(matches!(is_valid, Err(BidError::ContractWalletVerifier(_))));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bid_hostile_auction_names() {
+        let mut db = connected_rds_provider().await;
+
+        let hostile_auction_names = [
+            "LeafyGreens'; DROP TABLE bids; --",
+            "LeafyGreens' OR '1'='1",
+            "LeafyGreens\\'); COMMIT; DELETE FROM bids; BEGIN; --",
+            "$1 $$ $body$ ",
+        ];
+        for auction_name in hostile_auction_names {
+            let bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, auction_name);
+
+            let id = db.insert_bid(&bid).await.unwrap().id;
+            let bid_record = db.get_bid(&id).await.unwrap().unwrap();
+            assert_eq!(bid_record.auction_name, auction_name);
+            assert_eq!(bid_record.status, "submitted");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bid_resubmission_is_idempotent() {
+        let mut db = connected_rds_provider().await;
+
+        // A new auction each run, so the first insert is never a resubmission
+        let auction_name = format!(
+            "LeafyGreensResubmission{}",
+            chrono::Utc::now().timestamp_micros()
+        );
+        let bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, &auction_name);
+        let resubmitted_bid =
+            dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, &auction_name);
+
+        let stored_bid = db.insert_bid(&bid).await.unwrap();
+        let resubmitted_bid = db.insert_bid(&resubmitted_bid).await.unwrap();
+        assert_eq!(stored_bid.id, "0x".to_string() + &bid.hash());
+        assert!(stored_bid.inserted);
+        assert_eq!(resubmitted_bid.id, stored_bid.id);
+        assert!(!resubmitted_bid.inserted);
+        assert_eq!(resubmitted_bid.status, "submitted");
+        let bid_record = db.get_bid(&stored_bid.id).await.unwrap().unwrap();
+        assert_eq!(
+            bid_record.submitted_timestamp.timestamp_micros(),
+            bid.received_time.timestamp_micros()
+        );
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bid_empty_signature() {
+        let mut db = connected_rds_provider().await;
+
+        let mut bid = dummy_data::new_bid(
+            dummy_data::BidPayloadOption::Valid,
+            "LeafyGreensEmptySignature",
+        );
+        bid.payload.signature = "0x".to_string();
+
+        let id = db.insert_bid(&bid).await.unwrap().id;
+        let bid_record = db.get_bid(&id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "submitted");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bids_in_one_transaction() {
+        let mut db = connected_rds_provider().await;
+
+        let bids: Vec<Bid> = ["LeafyGreensBatchA", "LeafyGreensBatchB"]
+            .iter()
+            .map(|auction_name| {
+                dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, auction_name)
+            })
+            .collect();
+
+        let stored_bids = db.insert_bids(&bids).await.unwrap();
+        assert_eq!(stored_bids.len(), 2);
+        for (bid, stored_bid) in bids.iter().zip(stored_bids) {
+            let bid_record = db.get_bid(&stored_bid.unwrap().id).await.unwrap().unwrap();
+            assert_eq!(bid_record.auction_name, bid.parsed_values.auction_name);
+            assert_eq!(bid_record.status, "submitted");
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bids_replaced_in_batch() {
+        let mut db = connected_rds_provider().await;
+
+        // A new auction each run, so neither bid is already stored
+        let auction_name = format!(
+            "LeafyGreensBatchReplaced{}",
+            chrono::Utc::now().timestamp_micros()
+        );
+        let bids = vec![
+            dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, &auction_name),
+            dummy_data::new_bid(dummy_data::BidPayloadOption::Nonce, &auction_name),
+        ];
+
+        let stored_bids = db.insert_bids(&bids).await.unwrap();
+        let replaced = stored_bids[0].as_ref().unwrap();
+        assert_eq!(replaced.status, "replaced");
+        assert_eq!(stored_bids[1].as_ref().unwrap().status, "submitted");
+        let bid_record = db.get_bid(&replaced.id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "replaced");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bids_rejects_only_stale_nonce() {
+        let mut db = connected_rds_provider().await;
+
+        // New auctions each run, so no bid is already stored
+        let run = chrono::Utc::now().timestamp_micros();
+        let auction_name = format!("LeafyGreensBatchStaleNonce{}", run);
+        let bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Nonce, &auction_name);
+        db.insert_bid(&bid).await.unwrap();
+        // As if validated before the first bid was stored
+        let mut stale_bid = bid.clone();
+        stale_bid.typed_data_hash = [1; 32];
+        stale_bid.parsed_values.nonce = Some(2.into());
+        let other_bid = dummy_data::new_bid(
+            dummy_data::BidPayloadOption::Valid,
+            &format!("LeafyGreensBatchOther{}", run),
+        );
+
+        let stored_bids = db.insert_bids(&[stale_bid, other_bid]).await.unwrap();
+        assert_eq!(
+            stored_bids[0],
+            Err(BidError::StaleNonce {
+                nonce: 2.into(),
+                highest: 3.into()
+            })
+        );
+        let other_stored_bid = stored_bids[1].as_ref().unwrap();
+        let bid_record = db.get_bid(&other_stored_bid.id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "submitted");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_get_signer_exposure_excludes_replaced_auction() {
+        let mut db = connected_rds_provider().await;
+
+        // A settlement contract no other test stores bids against
+        let settlement_contract: Address = "0x00000000000000000000000000000000000e1016"
+            .parse()
+            .unwrap();
+        let bids: Vec<Bid> = ["LeafyGreensExposureA", "LeafyGreensExposureB"]
+            .iter()
+            .map(|auction_name| {
+                let mut bid =
+                    dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, auction_name);
+                bid.auction.settlement_contract = settlement_contract;
+                bid
+            })
+            .collect();
+        db.insert_bids(&bids).await.unwrap();
+
+        let exposure = db
+            .get_signer_exposure(
+                &bids[0].payload.sender.parse().unwrap(),
+                &settlement_contract,
+                &bids[0].auction.address,
+                &bids[0].parsed_values.auction_name,
+            )
+            .await
+            .unwrap();
+        assert_eq!(exposure, bids[1].parsed_values.get_bid_cost());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bid_normalizes_signer() {
+        let mut db = connected_rds_provider().await;
+
+        // A new auction and settlement contract each run, so only this bid
+        // counts towards the exposure
+        let run = chrono::Utc::now().timestamp_micros();
+        let settlement_contract = Address::from_low_u64_be(run as u64);
+        let mut bid = dummy_data::new_bid(
+            dummy_data::BidPayloadOption::Valid,
+            &format!("LeafyGreensSigner{}", run),
+        );
+        bid.auction.settlement_contract = settlement_contract;
+        // Senders parse without 0x and in any case
+        bid.payload.sender = bid.payload.sender[2..].to_uppercase();
+        let stored_bid = db.insert_bid(&bid).await.unwrap();
+
+        let signer: Address = bid.payload.sender.parse().unwrap();
+        let exposure = db
+            .get_signer_exposure(
+                &signer,
+                &settlement_contract,
+                &bid.auction.address,
+                "LeafyGreensOtherAuction",
+            )
+            .await
+            .unwrap();
+        assert_eq!(exposure, bid.parsed_values.get_bid_cost());
+        let bid_record = db.get_bid(&stored_bid.id).await.unwrap().unwrap();
+        assert_eq!(bid_record.signer, format!("{:?}", signer));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_cancel_bid_only_cancels_named_bid() {
+        let mut db = connected_rds_provider().await;
+
+        // A new auction each run, so the first bid is never already stored
+        let auction_name = format!("LeafyGreensCancel{}", chrono::Utc::now().timestamp_micros());
+        let old_bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, &auction_name);
+        let new_bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Nonce, &auction_name);
+        let old_id = db.insert_bid(&old_bid).await.unwrap().id;
+        let new_id = db.insert_bid(&new_bid).await.unwrap().id;
+        let resubmitted_bid = db.insert_bid(&old_bid).await.unwrap();
+        assert!(!resubmitted_bid.inserted);
+        assert_eq!(resubmitted_bid.status, "replaced");
+
+        // A Cancel signed for the replaced bid doesn't withdraw its replacement
+        let signer: Address = old_bid.payload.sender.parse().unwrap();
+        let cancelled = db
+            .cancel_bid(&old_id, &old_bid.auction.address, &auction_name, &signer)
+            .await
+            .unwrap();
+        assert_eq!(cancelled, None);
+        let bid_record = db.get_bid(&new_id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "submitted");
+
+        let cancelled = db
+            .cancel_bid(&new_id, &new_bid.auction.address, &auction_name, &signer)
+            .await
+            .unwrap();
+        assert_eq!(cancelled, Some(new_id.clone()));
+        let bid_record = db.get_bid(&new_id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "cancelled");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bid_without_nonce_after_nonce() {
+        let mut db = connected_rds_provider().await;
+
+        // A new auction each run, so neither bid is already stored
+        let auction_name = format!(
+            "LeafyGreensWithoutNonce{}",
+            chrono::Utc::now().timestamp_micros()
+        );
+        let bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Nonce, &auction_name);
+        let bid_without_nonce =
+            dummy_data::new_bid(dummy_data::BidPayloadOption::Valid, &auction_name);
+
+        let stored_bid = db.insert_bid(&bid).await.unwrap();
+        let err = db.insert_bid(&bid_without_nonce).await.unwrap_err();
+        assert_eq!(
+            err,
+            BidError::StaleNonce {
+                nonce: 0.into(),
+                highest: 3.into()
+            }
+        );
+        let bid_record = db.get_bid(&stored_bid.id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "submitted");
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_get_highest_nonce_excludes_bid() {
+        let mut db = connected_rds_provider().await;
+
+        // An auction no other test stores bids in
+        let bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Nonce, "LeafyGreensNonce");
+        db.insert_bid(&bid).await.unwrap();
+
+        let signer: Address = bid.payload.sender.parse().unwrap();
+        let highest = db
+            .get_highest_nonce(&signer, &bid.auction.address, "LeafyGreensNonce", "0x00")
+            .await
+            .unwrap();
+        assert_eq!(highest, Some(3.into()));
+        let highest = db
+            .get_highest_nonce(
+                &signer,
+                &bid.auction.address,
+                "LeafyGreensNonce",
+                &("0x".to_string() + &bid.hash()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(highest, None);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn rds_provider_insert_bid_rejects_stale_nonce() {
+        let mut db = connected_rds_provider().await;
+
+        // As if both Bids were validated before either was stored
+        let auction_name = format!(
+            "LeafyGreensStaleNonce{}",
+            chrono::Utc::now().timestamp_micros()
+        );
+        let bid = dummy_data::new_bid(dummy_data::BidPayloadOption::Nonce, &auction_name);
+        let mut stale_bid = bid.clone();
+        stale_bid.typed_data_hash = [1; 32];
+        stale_bid.parsed_values.nonce = Some(2.into());
+
+        let stored_bid = db.insert_bid(&bid).await.unwrap();
+        let err = db.insert_bid(&stale_bid).await.unwrap_err();
+        assert_eq!(
+            err,
+            BidError::StaleNonce {
+                nonce: 2.into(),
+                highest: 3.into()
+            }
+        );
+        let bid_record = db.get_bid(&stored_bid.id).await.unwrap().unwrap();
+        assert_eq!(bid_record.status, "submitted");
+        assert!(db
+            .get_bid(&("0x".to_string() + &stale_bid.hash()))
+            .await
+            .unwrap()
+            .is_none());
+    }
 }